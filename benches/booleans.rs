@@ -0,0 +1,64 @@
+//! Compares the inline `Object::Boolean` and `Object::Null` with shared `TRUE`/`FALSE`/`NULL`
+//! objects like those of the reference implementation. The evaluator returns owned
+//! `Object`s, so a shared object is handed out as a clone, or as an `Rc` if the
+//! evaluator were changed to return those.
+//!
+//! Run with `cargo bench --bench booleans`.
+
+use std::{hint::black_box, rc::Rc, time::Instant};
+
+use rmonkey::{evaluator::Evaluator, object::Object};
+
+const ITERATIONS: u32 = 10_000_000;
+
+const LOOP: &str = "
+let n = 0;
+let t = 0;
+while (n < 200000) {
+    if (!(n == 3) && (n < 100000 || n > 150000)) { t = t + 1; }
+    n = n + 1;
+}
+t
+";
+
+fn time(name: &str, mut f: impl FnMut()) {
+    let start = Instant::now();
+    f();
+    println!("{:<20} {:>10.2?}", name, start.elapsed());
+}
+
+fn main() {
+    time("boolean-heavy loop", || {
+        let r = Evaluator::new().run(LOOP).unwrap();
+        assert_eq!(r.to_string(), "149998");
+    });
+
+    time("inline", || {
+        for i in 0..ITERATIONS {
+            let b = Object::Boolean(black_box(i) % 3 == 0);
+            black_box(&b);
+            black_box(b.is_truthy());
+            black_box(&Object::Null);
+        }
+    });
+
+    let (t, f, null) = (Object::Boolean(true), Object::Boolean(false), Object::Null);
+    time("shared, cloned", || {
+        for i in 0..ITERATIONS {
+            let b = if black_box(i) % 3 == 0 { &t } else { &f }.clone();
+            black_box(&b);
+            black_box(b.is_truthy());
+            black_box(&null.clone());
+        }
+    });
+
+    let (t, f, null) = (Rc::new(t), Rc::new(f), Rc::new(null));
+    time("shared, Rc", || {
+        for i in 0..ITERATIONS {
+            let b = Rc::clone(if black_box(i) % 3 == 0 { &t } else { &f });
+            black_box(&b);
+            black_box(b.is_truthy());
+            black_box(&Rc::clone(&null));
+        }
+    });
+}
@@ -2,7 +2,8 @@ use std::fmt;
 
 use crate::operator::{Infix, Prefix};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Program {
     pub stmts: Vec<Stmt>,
 }
@@ -13,27 +14,81 @@ impl Program {
     }
 }
 
+#[cfg(feature = "serde")]
+impl Program {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("an AST is always serializable")
+    }
+}
+
+impl Program {
+    /// Renders the AST as a tree with one node per line, each nesting level indented
+    /// by `indent` spaces. Unlike `Display` this names every node kind.
+    pub fn pretty(&self, indent: usize) -> String {
+        let mut out = String::new();
+        push_line(&mut out, indent, 0, "Program");
+        for stmt in self.stmts.iter() {
+            stmt.pretty_into(&mut out, indent, 1);
+        }
+        out
+    }
+}
+
+fn push_line(out: &mut String, indent: usize, level: usize, text: &str) {
+    out.push_str(&" ".repeat(indent * level));
+    out.push_str(text);
+    out.push('\n');
+}
+
 impl Default for Program {
     fn default() -> Self {
         Self::new()
     }
 }
 
+/// Each statement is terminated with `;` so the output parses back into the same program;
+/// without it a statement starting with `(` would read as a call on the previous line.
 impl fmt::Display for Program {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         for stmt in self.stmts.iter() {
-            writeln!(f, "{}", stmt)?;
+            writeln!(f, "{};", stmt)?;
         }
         Ok(())
     }
 }
 
-#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Stmt {
-    LetStatement { ident: Expr, value: Expr },
-    ReturnStatement { value: Expr },
-    ExpressionStatement { expr: Expr },
-    BlockStatement { stmts: Vec<Stmt> },
+    LetStatement {
+        ident: Expr,
+        value: Expr,
+    },
+    AssignStatement {
+        target: Expr,
+        value: Expr,
+    },
+    ReturnStatement {
+        value: Expr,
+    },
+    BreakStatement,
+    ContinueStatement,
+    ExpressionStatement {
+        expr: Expr,
+    },
+    BlockStatement {
+        stmts: Vec<Stmt>,
+    },
+    WhileStatement {
+        condition: Expr,
+        body: Box<Stmt>,
+    },
+    ForStatement {
+        init: Box<Stmt>,
+        condition: Expr,
+        post: Box<Stmt>,
+        body: Box<Stmt>,
+    },
 }
 
 impl fmt::Display for Stmt {
@@ -42,28 +97,154 @@ impl fmt::Display for Stmt {
             Stmt::LetStatement { ident, value } => {
                 write!(f, "let {} = {}", ident, value)
             }
+            Stmt::AssignStatement { target, value } => {
+                write!(f, "{} = {}", target, value)
+            }
             Stmt::ReturnStatement { value } => {
                 write!(f, "return {}", value)
             }
+            Stmt::BreakStatement => write!(f, "break"),
+            Stmt::ContinueStatement => write!(f, "continue"),
             Stmt::ExpressionStatement { expr } => {
                 write!(f, "{}", expr)
             }
             Stmt::BlockStatement { stmts } => {
-                for stmt in stmts.iter() {
-                    write!(f, "{}", stmt)?;
-                }
-                Ok(())
+                let stmts = stmts.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+                write!(f, "{}", stmts.join("; "))
+            }
+            Stmt::WhileStatement { condition, body } => {
+                write!(f, "while({}){{{}}}", condition, body)
             }
+            Stmt::ForStatement {
+                init,
+                condition,
+                post,
+                body,
+            } => write!(f, "for({}; {}; {}){{{}}}", init, condition, post, body),
         }
     }
 }
 
-#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+/// Expressions nest as deep as the source does, e.g. `1 + 2 + ... + n` is one level per term,
+/// so a statement takes its expressions and the statements nested in them (block, loop, `if`
+/// and function bodies) apart with a work list instead of letting each `Box` drop its
+/// children recursively.
+impl Drop for Stmt {
+    fn drop(&mut self) {
+        let mut pending = Vec::new();
+        take_children(self, &mut pending);
+        while let Some(node) = pending.pop() {
+            match node {
+                // each taken statement drops here with only leaves left in it
+                Node::Stmt(mut stmt) => take_children(&mut stmt, &mut pending),
+                Node::Expr(mut expr) => match &mut expr {
+                    Expr::PrefixExpr { right, .. } => pending.push(Node::Expr(take(right))),
+                    Expr::InfixExpr { left, right, .. }
+                    | Expr::IndexExpr { left, index: right } => {
+                        pending.push(Node::Expr(take(left)));
+                        pending.push(Node::Expr(take(right)));
+                    }
+                    Expr::IfExpr {
+                        condition,
+                        consequence,
+                        alternative,
+                    } => {
+                        pending.push(Node::Expr(take(condition)));
+                        pending.push(Node::Stmt(take_stmt(consequence)));
+                        if let Some(alternative) = alternative {
+                            pending.push(Node::Stmt(take_stmt(alternative)));
+                        }
+                    }
+                    Expr::ConditionalExpr {
+                        condition,
+                        consequence,
+                        alternative,
+                    } => {
+                        pending.push(Node::Expr(take(condition)));
+                        pending.push(Node::Expr(take(consequence)));
+                        pending.push(Node::Expr(take(alternative)));
+                    }
+                    Expr::CallExpr { function, args } => {
+                        pending.push(Node::Expr(take(function)));
+                        pending.extend(args.drain(..).map(Node::Expr));
+                    }
+                    Expr::FuncLiteral { parameters, body } => {
+                        pending.extend(parameters.drain(..).map(Node::Expr));
+                        pending.push(Node::Stmt(take_stmt(body)));
+                    }
+                    Expr::ArrayLiteral { elements } => {
+                        pending.extend(elements.drain(..).map(Node::Expr))
+                    }
+                    Expr::HashLiteral { pairs } => {
+                        for (key, value) in pairs.drain(..) {
+                            pending.push(Node::Expr(key));
+                            pending.push(Node::Expr(value));
+                        }
+                    }
+                    _ => {}
+                },
+            }
+        }
+    }
+}
+
+/// A node waiting to be taken apart by `Stmt::drop`.
+enum Node {
+    Stmt(Stmt),
+    Expr(Expr),
+}
+
+/// Moves the direct children of `stmt` to `pending`, leaving leaves in their place.
+fn take_children(stmt: &mut Stmt, pending: &mut Vec<Node>) {
+    match stmt {
+        Stmt::LetStatement { ident, value } => {
+            pending.push(Node::Expr(take(ident)));
+            pending.push(Node::Expr(take(value)));
+        }
+        Stmt::AssignStatement { target, value } => {
+            pending.push(Node::Expr(take(target)));
+            pending.push(Node::Expr(take(value)));
+        }
+        Stmt::ReturnStatement { value } | Stmt::ExpressionStatement { expr: value } => {
+            pending.push(Node::Expr(take(value)))
+        }
+        Stmt::BreakStatement | Stmt::ContinueStatement => {}
+        Stmt::BlockStatement { stmts } => pending.extend(stmts.drain(..).map(Node::Stmt)),
+        Stmt::WhileStatement { condition, body } => {
+            pending.push(Node::Expr(take(condition)));
+            pending.push(Node::Stmt(take_stmt(body)));
+        }
+        Stmt::ForStatement {
+            init,
+            condition,
+            post,
+            body,
+        } => {
+            pending.push(Node::Stmt(take_stmt(init)));
+            pending.push(Node::Expr(take(condition)));
+            pending.push(Node::Stmt(take_stmt(post)));
+            pending.push(Node::Stmt(take_stmt(body)));
+        }
+    }
+}
+
+fn take(expr: &mut Expr) -> Expr {
+    std::mem::replace(expr, Expr::Null)
+}
+
+fn take_stmt(stmt: &mut Stmt) -> Stmt {
+    std::mem::replace(stmt, Stmt::BreakStatement)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Expr {
     Ident(String),
     String(String),
     Int(i64),
+    Float(f64),
     Boolean(bool),
+    Null,
     PrefixExpr {
         op: Prefix,
         right: Box<Expr>,
@@ -78,6 +259,12 @@ pub enum Expr {
         consequence: Box<Stmt>,
         alternative: Option<Box<Stmt>>,
     },
+    /// `condition ? consequence : alternative`
+    ConditionalExpr {
+        condition: Box<Expr>,
+        consequence: Box<Expr>,
+        alternative: Box<Expr>,
+    },
     FuncLiteral {
         parameters: Vec<Expr>,
         body: Box<Stmt>,
@@ -104,7 +291,9 @@ impl fmt::Display for Expr {
             Expr::Ident(ident) => write!(f, "{}", ident),
             Expr::String(val) => write!(f, r#""{}""#, val),
             Expr::Int(val) => write!(f, "{}", val),
+            Expr::Float(val) => write!(f, "{}", fmt_float(*val)),
             Expr::Boolean(val) => write!(f, "{}", val),
+            Expr::Null => write!(f, "null"),
             Expr::PrefixExpr { op, right } => write!(f, "({}{})", op, right),
             Expr::InfixExpr { left, right, op } => write!(f, "({} {} {})", left, op, right),
             Expr::IfExpr {
@@ -115,6 +304,11 @@ impl fmt::Display for Expr {
                 Some(alt) => write!(f, "if({}){{{}}}else{{{}}}", condition, consequence, alt),
                 None => write!(f, "if({}){{{}}}", condition, consequence),
             },
+            Expr::ConditionalExpr {
+                condition,
+                consequence,
+                alternative,
+            } => write!(f, "({} ? {} : {})", condition, consequence, alternative),
             Expr::FuncLiteral { parameters, body } => {
                 let params = if parameters.len() == 1 {
                     format!("{}", parameters[0])
@@ -158,3 +352,235 @@ impl fmt::Display for Expr {
         }
     }
 }
+
+impl Stmt {
+    fn pretty_into(&self, out: &mut String, indent: usize, level: usize) {
+        let next = level + 1;
+        match self {
+            Stmt::LetStatement { ident, value } => {
+                push_line(out, indent, level, "LetStatement");
+                ident.pretty_into(out, indent, next);
+                value.pretty_into(out, indent, next);
+            }
+            Stmt::AssignStatement { target, value } => {
+                push_line(out, indent, level, "AssignStatement");
+                target.pretty_into(out, indent, next);
+                value.pretty_into(out, indent, next);
+            }
+            Stmt::ReturnStatement { value } => {
+                push_line(out, indent, level, "ReturnStatement");
+                value.pretty_into(out, indent, next);
+            }
+            Stmt::BreakStatement => push_line(out, indent, level, "BreakStatement"),
+            Stmt::ContinueStatement => push_line(out, indent, level, "ContinueStatement"),
+            Stmt::ExpressionStatement { expr } => {
+                push_line(out, indent, level, "ExpressionStatement");
+                expr.pretty_into(out, indent, next);
+            }
+            Stmt::BlockStatement { stmts } => {
+                push_line(out, indent, level, "BlockStatement");
+                for stmt in stmts.iter() {
+                    stmt.pretty_into(out, indent, next);
+                }
+            }
+            Stmt::WhileStatement { condition, body } => {
+                push_line(out, indent, level, "WhileStatement");
+                condition.pretty_into(out, indent, next);
+                body.pretty_into(out, indent, next);
+            }
+            Stmt::ForStatement {
+                init,
+                condition,
+                post,
+                body,
+            } => {
+                push_line(out, indent, level, "ForStatement");
+                init.pretty_into(out, indent, next);
+                condition.pretty_into(out, indent, next);
+                post.pretty_into(out, indent, next);
+                body.pretty_into(out, indent, next);
+            }
+        }
+    }
+}
+
+impl Expr {
+    fn pretty_into(&self, out: &mut String, indent: usize, level: usize) {
+        let next = level + 1;
+        match self {
+            Expr::Ident(val) => push_line(out, indent, level, &format!("Ident {}", val)),
+            Expr::String(val) => push_line(out, indent, level, &format!(r#"String "{}""#, val)),
+            Expr::Int(val) => push_line(out, indent, level, &format!("Int {}", val)),
+            Expr::Float(val) => {
+                push_line(out, indent, level, &format!("Float {}", fmt_float(*val)))
+            }
+            Expr::Boolean(val) => push_line(out, indent, level, &format!("Boolean {}", val)),
+            Expr::Null => push_line(out, indent, level, "Null"),
+            Expr::PrefixExpr { op, right } => {
+                push_line(out, indent, level, &format!("PrefixExpr {}", op));
+                right.pretty_into(out, indent, next);
+            }
+            Expr::InfixExpr { left, right, op } => {
+                push_line(out, indent, level, &format!("InfixExpr {}", op));
+                left.pretty_into(out, indent, next);
+                right.pretty_into(out, indent, next);
+            }
+            Expr::IfExpr {
+                condition,
+                consequence,
+                alternative,
+            } => {
+                push_line(out, indent, level, "IfExpr");
+                condition.pretty_into(out, indent, next);
+                consequence.pretty_into(out, indent, next);
+                if let Some(alternative) = alternative {
+                    alternative.pretty_into(out, indent, next);
+                }
+            }
+            Expr::ConditionalExpr {
+                condition,
+                consequence,
+                alternative,
+            } => {
+                push_line(out, indent, level, "ConditionalExpr");
+                condition.pretty_into(out, indent, next);
+                consequence.pretty_into(out, indent, next);
+                alternative.pretty_into(out, indent, next);
+            }
+            Expr::FuncLiteral { parameters, body } => {
+                push_line(out, indent, level, "FuncLiteral");
+                for param in parameters.iter() {
+                    param.pretty_into(out, indent, next);
+                }
+                body.pretty_into(out, indent, next);
+            }
+            Expr::CallExpr { function, args } => {
+                push_line(out, indent, level, "CallExpr");
+                function.pretty_into(out, indent, next);
+                for arg in args.iter() {
+                    arg.pretty_into(out, indent, next);
+                }
+            }
+            Expr::ArrayLiteral { elements } => {
+                push_line(out, indent, level, "ArrayLiteral");
+                for el in elements.iter() {
+                    el.pretty_into(out, indent, next);
+                }
+            }
+            Expr::IndexExpr { left, index } => {
+                push_line(out, indent, level, "IndexExpr");
+                left.pretty_into(out, indent, next);
+                index.pretty_into(out, indent, next);
+            }
+            Expr::HashLiteral { pairs } => {
+                push_line(out, indent, level, "HashLiteral");
+                for (key, value) in pairs.iter() {
+                    push_line(out, indent, next, "Pair");
+                    key.pretty_into(out, indent, next + 1);
+                    value.pretty_into(out, indent, next + 1);
+                }
+            }
+        }
+    }
+}
+
+/// Formats a float so that it never reads back as an integer, e.g. `3.0` instead of `3`.
+pub fn fmt_float(val: f64) -> String {
+    if val.is_finite() && val.fract() == 0.0 {
+        format!("{:.1}", val)
+    } else {
+        format!("{}", val)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Expr, Stmt};
+    use crate::{lexer::Lexer, parser::Parser};
+
+    #[test]
+    fn test_program_eq() {
+        let parse = |input| Parser::new(Lexer::new(input)).parse_program().unwrap();
+        let program = parse("let x = 1 + 2; if (x > 2) { x } else { 0 }");
+        assert_eq!(
+            program,
+            parse("let x = 1 + 2;\nif (x > 2) { x } else { 0 }")
+        );
+        assert_eq!(program, program.clone());
+        assert_ne!(program, parse("let x = 1 + 2; if (x > 2) { x } else { 1 }"));
+        assert_ne!(program, parse("let x = 1 + 2;"));
+    }
+
+    #[test]
+    fn test_pretty() {
+        let l = Lexer::new("let x = 1 + 2 * -a; f(x)[0]");
+        let mut p = Parser::new(l);
+        let program = p.parse_program().unwrap();
+        let expected = "\
+Program
+  LetStatement
+    Ident x
+    InfixExpr +
+      Int 1
+      InfixExpr *
+        Int 2
+        PrefixExpr -
+          Ident a
+  ExpressionStatement
+    IndexExpr
+      CallExpr
+        Ident f
+        Ident x
+      Int 0
+";
+        assert_eq!(program.pretty(2), expected);
+        assert!(program.pretty(4).contains("\n        Ident x\n"));
+    }
+
+    #[test]
+    fn test_drop_nested_long_chain() {
+        let chain = vec!["1"; 100_000].join(" + ");
+        let inputs = [
+            format!("let f = fn() {{ {} }};", chain),
+            format!("if (true) {{ {} }} else {{ [{}] }}", chain, chain),
+            format!("while (false) {{ let x = fn() {{ return {}; }}; }}", chain),
+            format!("for (let i = 0; false; i = {}) {{ {} }}", chain, chain),
+        ];
+        for input in inputs.iter() {
+            let program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+            drop(program);
+        }
+        // nested deeper than the parser would go, so built by hand
+        let mut stmt = Stmt::BreakStatement;
+        for _ in 0..100_000 {
+            stmt = Stmt::WhileStatement {
+                condition: Expr::Boolean(true),
+                body: Box::new(Stmt::BlockStatement { stmts: vec![stmt] }),
+            };
+        }
+        drop(stmt);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_to_json() {
+        let l = Lexer::new("let a = 1; return a + 2;");
+        let mut p = Parser::new(l);
+        let program = p.parse_program().unwrap();
+        let json = program.to_json();
+        assert!(json.contains("LetStatement"));
+        assert!(json.contains("ReturnStatement"));
+        assert!(json.contains("InfixExpr"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_object_json() {
+        let obj = crate::eval_str(r#"[1, "a", {"b": true}]"#).unwrap();
+        let json = serde_json::to_string(&obj).unwrap();
+        assert_eq!(
+            json,
+            r#"{"Array":{"elements":[{"Integer":1},{"String":"a"},{"Hash":{"pairs":[[{"String":"b"},{"Boolean":true}]]}}]}}"#
+        );
+    }
+}
@@ -1,11 +1,17 @@
+use std::{cmp::Ordering, collections::HashMap, sync::OnceLock};
+
 use crate::error::{MonkeyError, Result};
-use crate::object::Object;
+use crate::evaluator::{resolve_index, Evaluator};
+use crate::object::{BuiltinFn, Object};
 
 macro_rules! builtin {
     ($name:ident) => {
+        builtin!(stringify!($name), $name)
+    };
+    ($name:expr, $func:ident) => {
         BuiltIn {
-            name: stringify!($name),
-            builtin: Object::BuiltIn($name),
+            name: $name,
+            builtin: Object::BuiltIn($func),
         }
     };
 }
@@ -23,18 +29,69 @@ pub const BUILTIN: &[BuiltIn] = &[
     builtin!(rest),
     builtin!(push),
     builtin!(puts),
+    builtin!(map),
+    builtin!(filter),
+    builtin!(reduce),
+    builtin!(str),
+    builtin!(split),
+    builtin!(join),
+    builtin!("type", type_of),
+    builtin!(range),
+    builtin!(keys),
+    builtin!(values),
+    builtin!(delete),
+    builtin!("clone", deep_clone),
+    builtin!(sort),
+    builtin!(reverse),
+    builtin!(min),
+    builtin!(max),
+    builtin!(sum),
+    builtin!(print),
+    builtin!(println),
+    builtin!(upper),
+    builtin!(lower),
+    builtin!(trim),
+    builtin!(index_of),
+    builtin!(abs),
+    builtin!(pow),
+    builtin!(sqrt),
+    builtin!(assert),
+    builtin!(import),
+    builtin!(repeat),
+    builtin!(each),
+    builtin!(chars),
+    builtin!(bytes),
+    builtin!(concat),
+    builtin!(set),
+    builtin!(format),
 ];
 
+/// Largest array `range` will build, to avoid running out of memory.
+pub const RANGE_LIMIT: i64 = 10_000_000;
+
+/// Most elements `repeat` will create, to avoid running out of memory.
+/// Every copy of an array or hash counts all the elements nested in it.
+pub const REPEAT_LIMIT: i64 = 10_000_000;
+
+/// Finds the builtin called `name`.
+/// The table is indexed into a `HashMap` on first use, so this is O(1) rather than a
+/// linear scan of `BUILTIN` on every call.
 pub fn lookup(name: &str) -> Option<Object> {
-    for func in BUILTIN {
-        if func.name == name {
-            return Some(func.builtin.clone());
-        }
-    }
-    None
+    // `Object` isn't `Sync`, so the index holds the plain function pointers
+    static INDEX: OnceLock<HashMap<&'static str, BuiltinFn>> = OnceLock::new();
+    let index = INDEX.get_or_init(|| {
+        BUILTIN
+            .iter()
+            .filter_map(|func| match func.builtin {
+                Object::BuiltIn(f) => Some((func.name, f)),
+                _ => None,
+            })
+            .collect()
+    });
+    index.get(name).map(|f| Object::BuiltIn(*f))
 }
 
-fn len(args: Vec<Object>) -> Result<Object> {
+fn len(_: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
     if args.len() != 1 {
         return Err(MonkeyError::Custom(format!(
             "wrong number of arguments. got={}, want=1",
@@ -54,7 +111,7 @@ fn len(args: Vec<Object>) -> Result<Object> {
     }
 }
 
-fn first(args: Vec<Object>) -> Result<Object> {
+fn first(_: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
     if args.len() != 1 {
         return Err(MonkeyError::Custom(format!(
             "wrong number of arguments. got={}, want=1",
@@ -66,7 +123,7 @@ fn first(args: Vec<Object>) -> Result<Object> {
             if elements.is_empty() {
                 return Err(MonkeyError::Custom("this array is empty".to_string()));
             }
-            match elements.get(0) {
+            match elements.first() {
                 Some(obj) => Ok(obj.clone()),
                 None => Err(MonkeyError::Custom(format!(
                     "wrong number of arguments. got={}, want=1",
@@ -74,6 +131,10 @@ fn first(args: Vec<Object>) -> Result<Object> {
                 ))),
             }
         }
+        Object::String(val) => match val.chars().next() {
+            Some(c) => Ok(Object::String(c.to_string())),
+            None => Err(MonkeyError::Custom("this string is empty".to_string())),
+        },
         arg => Err(MonkeyError::Custom(format!(
             "arg to `first` not supported, got {}",
             arg.obj_type()
@@ -81,7 +142,7 @@ fn first(args: Vec<Object>) -> Result<Object> {
     }
 }
 
-fn last(args: Vec<Object>) -> Result<Object> {
+fn last(_: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
     if args.len() != 1 {
         return Err(MonkeyError::Custom(format!(
             "wrong number of arguments. got={}, want=1",
@@ -102,6 +163,10 @@ fn last(args: Vec<Object>) -> Result<Object> {
                 ))),
             }
         }
+        Object::String(val) => match val.chars().last() {
+            Some(c) => Ok(Object::String(c.to_string())),
+            None => Err(MonkeyError::Custom("this string is empty".to_string())),
+        },
         arg => Err(MonkeyError::Custom(format!(
             "arg to `last` not supported, got {}",
             arg.obj_type()
@@ -109,7 +174,7 @@ fn last(args: Vec<Object>) -> Result<Object> {
     }
 }
 
-fn rest(args: Vec<Object>) -> Result<Object> {
+fn rest(_: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
     if args.len() != 1 {
         return Err(MonkeyError::Custom(format!(
             "wrong number of arguments. got={}, want=1",
@@ -124,14 +189,21 @@ fn rest(args: Vec<Object>) -> Result<Object> {
             let elements: Vec<Object> = elements.clone().drain(1..).collect();
             Ok(Object::Array { elements })
         }
+        Object::String(val) => {
+            let mut chars = val.chars();
+            match chars.next() {
+                Some(_) => Ok(Object::String(chars.collect())),
+                None => Err(MonkeyError::Custom("this string is empty".to_string())),
+            }
+        }
         arg => Err(MonkeyError::Custom(format!(
-            "arg to `last` not supported, got {}",
+            "arg to `rest` not supported, got {}",
             arg.obj_type()
         ))),
     }
 }
 
-fn push(args: Vec<Object>) -> Result<Object> {
+fn push(_: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
     if args.len() != 2 {
         return Err(MonkeyError::Custom(format!(
             "wrong number of arguments. got={}, want=2",
@@ -153,9 +225,885 @@ fn push(args: Vec<Object>) -> Result<Object> {
     }
 }
 
-fn puts(args: Vec<Object>) -> Result<Object> {
-    for a in args.iter() {
-        println!("{}", a);
+/// `concat(a, b, ...)` joins any number of arrays into a new one, in argument order.
+/// With no arguments the result is `[]`.
+fn concat(_: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
+    let mut result = Vec::new();
+    for arg in args {
+        match arg {
+            Object::Array { elements } => result.extend(elements),
+            arg => {
+                return Err(MonkeyError::Custom(format!(
+                    "arg to `concat` not supported, got {}",
+                    arg.obj_type()
+                )))
+            }
+        }
+    }
+    Ok(Object::Array { elements: result })
+}
+
+/// `set(arr, index, value)` gives a copy of `arr` with the element at `index` replaced.
+/// Like indexing, a negative index counts from the end.
+fn set(_: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
+    if args.len() != 3 {
+        return Err(MonkeyError::Custom(format!(
+            "wrong number of arguments. got={}, want=3",
+            args.len()
+        )));
+    }
+    let mut args = args.into_iter();
+    let (arr, index, value) = (
+        args.next().unwrap(),
+        args.next().unwrap(),
+        args.next().unwrap(),
+    );
+    match (arr, index) {
+        (Object::Array { mut elements }, Object::Integer(index)) => {
+            let i = resolve_index(index, elements.len())
+                .ok_or(MonkeyError::IndexOutOfRange(index, elements.len()))?;
+            elements[i] = value;
+            Ok(Object::Array { elements })
+        }
+        (Object::Array { .. }, arg) | (arg, _) => Err(MonkeyError::Custom(format!(
+            "arg to `set` not supported, got {}",
+            arg.obj_type()
+        ))),
+    }
+}
+
+/// Prints each argument on its own line. `puts` is only for its side effect
+/// and always returns `null`.
+fn puts(e: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
+    let out: String = args.iter().map(|a| format!("{}\n", a)).collect();
+    e.write_out(&out)?;
+    Ok(Object::Null)
+}
+
+fn print(e: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
+    if args.len() != 1 {
+        return Err(MonkeyError::Custom(format!(
+            "wrong number of arguments. got={}, want=1",
+            args.len()
+        )));
+    }
+    let s = stringify(&args[0]);
+    e.write_out(&s)?;
+    Ok(Object::String(s))
+}
+
+fn println(e: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
+    if args.len() != 1 {
+        return Err(MonkeyError::Custom(format!(
+            "wrong number of arguments. got={}, want=1",
+            args.len()
+        )));
+    }
+    let s = stringify(&args[0]);
+    e.write_out(&format!("{}\n", s))?;
+    Ok(Object::String(s))
+}
+
+fn map(e: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
+    if args.len() != 2 {
+        return Err(MonkeyError::Custom(format!(
+            "wrong number of arguments. got={}, want=2",
+            args.len()
+        )));
+    }
+    let mut args = args.into_iter();
+    let (arr, func) = (args.next().unwrap(), args.next().unwrap());
+    let elements = match arr {
+        Object::Array { elements } => elements,
+        arg => {
+            return Err(MonkeyError::Custom(format!(
+                "arg to `map` not supported, got {}",
+                arg.obj_type()
+            )))
+        }
+    };
+    if !is_callable(&func) {
+        return Err(MonkeyError::Custom(format!(
+            "arg to `map` not supported, got {}",
+            func.obj_type()
+        )));
+    }
+    let mut result = Vec::with_capacity(elements.len());
+    for el in elements {
+        result.push(e.apply_function(func.clone(), vec![el])?);
+    }
+    Ok(Object::Array { elements: result })
+}
+
+/// `each(arr, f)` calls `f` on every element in order, for its side effects.
+fn each(e: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
+    if args.len() != 2 {
+        return Err(MonkeyError::Custom(format!(
+            "wrong number of arguments. got={}, want=2",
+            args.len()
+        )));
+    }
+    let mut args = args.into_iter();
+    let (arr, func) = (args.next().unwrap(), args.next().unwrap());
+    let elements = match arr {
+        Object::Array { elements } => elements,
+        arg => {
+            return Err(MonkeyError::Custom(format!(
+                "arg to `each` not supported, got {}",
+                arg.obj_type()
+            )))
+        }
+    };
+    if !is_callable(&func) {
+        return Err(MonkeyError::Custom(format!(
+            "arg to `each` not supported, got {}",
+            func.obj_type()
+        )));
+    }
+    for el in elements {
+        e.apply_function(func.clone(), vec![el])?;
     }
     Ok(Object::Null)
 }
+
+fn filter(e: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
+    if args.len() != 2 {
+        return Err(MonkeyError::Custom(format!(
+            "wrong number of arguments. got={}, want=2",
+            args.len()
+        )));
+    }
+    let mut args = args.into_iter();
+    let (arr, func) = (args.next().unwrap(), args.next().unwrap());
+    let elements = match arr {
+        Object::Array { elements } => elements,
+        arg => {
+            return Err(MonkeyError::Custom(format!(
+                "arg to `filter` not supported, got {}",
+                arg.obj_type()
+            )))
+        }
+    };
+    if !is_callable(&func) {
+        return Err(MonkeyError::Custom(format!(
+            "arg to `filter` not supported, got {}",
+            func.obj_type()
+        )));
+    }
+    let mut result = Vec::new();
+    for el in elements {
+        if e.apply_function(func.clone(), vec![el.clone()])?
+            .is_truthy()
+        {
+            result.push(el);
+        }
+    }
+    Ok(Object::Array { elements: result })
+}
+
+fn reduce(e: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
+    if args.len() != 3 {
+        return Err(MonkeyError::Custom(format!(
+            "wrong number of arguments. got={}, want=3",
+            args.len()
+        )));
+    }
+    let mut args = args.into_iter();
+    let (arr, initial, func) = (
+        args.next().unwrap(),
+        args.next().unwrap(),
+        args.next().unwrap(),
+    );
+    let elements = match arr {
+        Object::Array { elements } => elements,
+        arg => {
+            return Err(MonkeyError::Custom(format!(
+                "arg to `reduce` not supported, got {}",
+                arg.obj_type()
+            )))
+        }
+    };
+    if !is_callable(&func) {
+        return Err(MonkeyError::Custom(format!(
+            "arg to `reduce` not supported, got {}",
+            func.obj_type()
+        )));
+    }
+    let mut acc = initial;
+    for el in elements {
+        acc = e.apply_function(func.clone(), vec![acc, el])?;
+    }
+    Ok(acc)
+}
+
+/// Converts any object to a string using its `Display`.
+/// A string is returned as is, without surrounding quotes.
+fn str(_: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
+    if args.len() != 1 {
+        return Err(MonkeyError::Custom(format!(
+            "wrong number of arguments. got={}, want=1",
+            args.len()
+        )));
+    }
+    Ok(Object::String(stringify(&args[0])))
+}
+
+/// Splits a string on `sep`. An empty `sep` splits the string into its characters,
+/// and an empty string always gives an empty array.
+fn split(_: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
+    if args.len() != 2 {
+        return Err(MonkeyError::Custom(format!(
+            "wrong number of arguments. got={}, want=2",
+            args.len()
+        )));
+    }
+    match (&args[0], &args[1]) {
+        (Object::String(val), Object::String(sep)) => {
+            let elements = if val.is_empty() {
+                Vec::new()
+            } else if sep.is_empty() {
+                val.chars().map(|c| Object::String(c.to_string())).collect()
+            } else {
+                val.split(sep.as_str())
+                    .map(|s| Object::String(s.to_string()))
+                    .collect()
+            };
+            Ok(Object::Array { elements })
+        }
+        (Object::String(_), arg) | (arg, _) => Err(MonkeyError::Custom(format!(
+            "arg to `split` not supported, got {}",
+            arg.obj_type()
+        ))),
+    }
+}
+
+/// Joins the elements of an array with `sep`.
+/// Elements that are not strings are converted the same way `str` does.
+fn join(_: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
+    if args.len() != 2 {
+        return Err(MonkeyError::Custom(format!(
+            "wrong number of arguments. got={}, want=2",
+            args.len()
+        )));
+    }
+    match (&args[0], &args[1]) {
+        (Object::Array { elements }, Object::String(sep)) => Ok(Object::String(
+            elements
+                .iter()
+                .map(stringify)
+                .collect::<Vec<String>>()
+                .join(sep),
+        )),
+        (Object::Array { .. }, arg) | (arg, _) => Err(MonkeyError::Custom(format!(
+            "arg to `join` not supported, got {}",
+            arg.obj_type()
+        ))),
+    }
+}
+
+fn upper(_: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
+    map_string("upper", args, |s| s.to_uppercase())
+}
+
+fn lower(_: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
+    map_string("lower", args, |s| s.to_lowercase())
+}
+
+fn trim(_: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
+    map_string("trim", args, |s| s.trim().to_string())
+}
+
+/// Applies `f` to the single string argument of the builtin `name`.
+fn map_string(name: &str, args: Vec<Object>, f: impl Fn(&str) -> String) -> Result<Object> {
+    Ok(Object::String(f(string_arg(name, &args)?)))
+}
+
+/// The single string argument of the builtin `name`.
+fn string_arg<'a>(name: &str, args: &'a [Object]) -> Result<&'a str> {
+    if args.len() != 1 {
+        return Err(MonkeyError::Custom(format!(
+            "wrong number of arguments. got={}, want=1",
+            args.len()
+        )));
+    }
+    match &args[0] {
+        Object::String(val) => Ok(val),
+        arg => Err(MonkeyError::Custom(format!(
+            "arg to `{}` not supported, got {}",
+            name,
+            arg.obj_type()
+        ))),
+    }
+}
+
+/// `chars(s)` splits a string into one-character strings, one per Unicode scalar value,
+/// so `chars("é")` is `["é"]` even though it takes two bytes.
+fn chars(_: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
+    let val = string_arg("chars", &args)?;
+    Ok(Object::Array {
+        elements: val.chars().map(|c| Object::String(c.to_string())).collect(),
+    })
+}
+
+/// `bytes(s)` gives the UTF-8 encoding of a string as integers from 0 to 255.
+fn bytes(_: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
+    let val = string_arg("bytes", &args)?;
+    Ok(Object::Array {
+        elements: val.bytes().map(|b| Object::Integer(b.into())).collect(),
+    })
+}
+
+fn index_of(_: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
+    if args.len() != 2 {
+        return Err(MonkeyError::Custom(format!(
+            "wrong number of arguments. got={}, want=2",
+            args.len()
+        )));
+    }
+    let index = match (&args[0], &args[1]) {
+        (Object::Array { elements }, value) => elements.iter().position(|el| el == value),
+        // the index counts characters, matching how strings are indexed
+        (Object::String(val), Object::String(sub)) => {
+            val.find(sub.as_str()).map(|i| val[..i].chars().count())
+        }
+        (Object::String(_), arg) | (arg, _) => {
+            return Err(MonkeyError::Custom(format!(
+                "arg to `index_of` not supported, got {}",
+                arg.obj_type()
+            )))
+        }
+    };
+    Ok(Object::Integer(index.map_or(-1, |i| i as i64)))
+}
+
+fn assert(_: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
+    let (cond, message) = match args.as_slice() {
+        [cond] => (cond, None),
+        [cond, message] => (cond, Some(stringify(message))),
+        _ => {
+            return Err(MonkeyError::Custom(format!(
+                "wrong number of arguments. got={}, want=1 or 2",
+                args.len()
+            )))
+        }
+    };
+    if cond.is_truthy() {
+        Ok(Object::Null)
+    } else {
+        Err(MonkeyError::AssertionFailed(message))
+    }
+}
+
+fn import(e: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
+    if args.len() != 1 {
+        return Err(MonkeyError::Custom(format!(
+            "wrong number of arguments. got={}, want=1",
+            args.len()
+        )));
+    }
+    match &args[0] {
+        Object::String(path) => {
+            e.import(path)?;
+            Ok(Object::Null)
+        }
+        arg => Err(MonkeyError::Custom(format!(
+            "arg to `import` not supported, got {}",
+            arg.obj_type()
+        ))),
+    }
+}
+
+fn type_of(_: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
+    if args.len() != 1 {
+        return Err(MonkeyError::Custom(format!(
+            "wrong number of arguments. got={}, want=1",
+            args.len()
+        )));
+    }
+    Ok(Object::String(args[0].obj_type()))
+}
+
+/// `range(n)` gives `[0, ..., n-1]` and `range(start, end)` gives `[start, ..., end-1]`.
+/// The result is empty if `start >= end`.
+fn range(_: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
+    let (start, end) = match args.as_slice() {
+        [Object::Integer(end)] => (0, *end),
+        [Object::Integer(start), Object::Integer(end)] => (*start, *end),
+        [_] | [_, _] => {
+            let arg = args
+                .iter()
+                .find(|a| !matches!(a, Object::Integer(_)))
+                .unwrap();
+            return Err(MonkeyError::Custom(format!(
+                "arg to `range` not supported, got {}",
+                arg.obj_type()
+            )));
+        }
+        _ => {
+            return Err(MonkeyError::Custom(format!(
+                "wrong number of arguments. got={}, want=1 or 2",
+                args.len()
+            )))
+        }
+    };
+    if start >= end {
+        return Ok(Object::Array { elements: vec![] });
+    }
+    if end.checked_sub(start).is_none_or(|len| len > RANGE_LIMIT) {
+        return Err(MonkeyError::Custom(format!(
+            "range too large, at most {} elements are allowed",
+            RANGE_LIMIT
+        )));
+    }
+    let elements = (start..end).map(Object::Integer).collect();
+    Ok(Object::Array { elements })
+}
+
+fn keys(_: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
+    if args.len() != 1 {
+        return Err(MonkeyError::Custom(format!(
+            "wrong number of arguments. got={}, want=1",
+            args.len()
+        )));
+    }
+    match &args[0] {
+        Object::Hash { pairs } => Ok(Object::Array {
+            elements: pairs.iter().map(|(k, _)| k.clone()).collect(),
+        }),
+        arg => Err(MonkeyError::Custom(format!(
+            "arg to `keys` not supported, got {}",
+            arg.obj_type()
+        ))),
+    }
+}
+
+fn values(_: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
+    if args.len() != 1 {
+        return Err(MonkeyError::Custom(format!(
+            "wrong number of arguments. got={}, want=1",
+            args.len()
+        )));
+    }
+    match &args[0] {
+        Object::Hash { pairs } => Ok(Object::Array {
+            elements: pairs.iter().map(|(_, v)| v.clone()).collect(),
+        }),
+        arg => Err(MonkeyError::Custom(format!(
+            "arg to `values` not supported, got {}",
+            arg.obj_type()
+        ))),
+    }
+}
+
+fn delete(_: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
+    if args.len() != 2 {
+        return Err(MonkeyError::Custom(format!(
+            "wrong number of arguments. got={}, want=2",
+            args.len()
+        )));
+    }
+    match &args[0] {
+        Object::Hash { pairs } => {
+            args[1].check_hashable()?;
+            let pairs = pairs
+                .iter()
+                .filter(|(k, _)| *k != args[1])
+                .cloned()
+                .collect();
+            Ok(Object::Hash { pairs })
+        }
+        arg => Err(MonkeyError::Custom(format!(
+            "arg to `delete` not supported, got {}",
+            arg.obj_type()
+        ))),
+    }
+}
+
+fn deep_clone(_: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
+    if args.len() != 1 {
+        return Err(MonkeyError::Custom(format!(
+            "wrong number of arguments. got={}, want=1",
+            args.len()
+        )));
+    }
+    // arrays and hashes own their elements, so cloning copies nested collections too
+    Ok(args[0].clone())
+}
+
+fn sort(e: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
+    if args.is_empty() || args.len() > 2 {
+        return Err(MonkeyError::Custom(format!(
+            "wrong number of arguments. got={}, want=1 or 2",
+            args.len()
+        )));
+    }
+    let mut args = args.into_iter();
+    let mut elements = match args.next().unwrap() {
+        Object::Array { elements } => elements,
+        arg => {
+            return Err(MonkeyError::Custom(format!(
+                "arg to `sort` not supported, got {}",
+                arg.obj_type()
+            )))
+        }
+    };
+    if let Some(func) = args.next() {
+        if !is_callable(&func) {
+            return Err(MonkeyError::Custom(format!(
+                "arg to `sort` not supported, got {}",
+                func.obj_type()
+            )));
+        }
+        let elements = merge_sort(e, &func, elements)?;
+        return Ok(Object::Array { elements });
+    }
+    for el in elements.iter() {
+        match (&elements[0], el) {
+            (Object::Integer(_), Object::Integer(_)) | (Object::String(_), Object::String(_)) => {}
+            (Object::Integer(_), _) | (Object::String(_), _) => {
+                return Err(MonkeyError::Custom(format!(
+                    "cannot sort an array of mixed types: {} and {}",
+                    elements[0].obj_type(),
+                    el.obj_type()
+                )))
+            }
+            (first, _) => {
+                return Err(MonkeyError::Custom(format!(
+                    "cannot sort an array of {}",
+                    first.obj_type()
+                )))
+            }
+        }
+    }
+    elements.sort_by(|a, b| match (a, b) {
+        (Object::Integer(a), Object::Integer(b)) => a.cmp(b),
+        (Object::String(a), Object::String(b)) => a.cmp(b),
+        _ => unreachable!("elements are checked to be of the same sortable type"),
+    });
+    Ok(Object::Array { elements })
+}
+
+fn reverse(_: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
+    if args.len() != 1 {
+        return Err(MonkeyError::Custom(format!(
+            "wrong number of arguments. got={}, want=1",
+            args.len()
+        )));
+    }
+    match &args[0] {
+        Object::Array { elements } => Ok(Object::Array {
+            elements: elements.iter().rev().cloned().collect(),
+        }),
+        Object::String(val) => Ok(Object::String(val.chars().rev().collect())),
+        arg => Err(MonkeyError::Custom(format!(
+            "arg to `reverse` not supported, got {}",
+            arg.obj_type()
+        ))),
+    }
+}
+
+fn min(_: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
+    let elements = integer_elements("min", &args)?;
+    match elements.into_iter().min() {
+        Some(val) => Ok(Object::Integer(val)),
+        None => Err(MonkeyError::Custom(
+            "cannot take `min` of an empty array".to_string(),
+        )),
+    }
+}
+
+fn max(_: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
+    let elements = integer_elements("max", &args)?;
+    match elements.into_iter().max() {
+        Some(val) => Ok(Object::Integer(val)),
+        None => Err(MonkeyError::Custom(
+            "cannot take `max` of an empty array".to_string(),
+        )),
+    }
+}
+
+fn sum(_: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
+    let elements = integer_elements("sum", &args)?;
+    let mut total: i64 = 0;
+    for val in elements {
+        total = total
+            .checked_add(val)
+            .ok_or_else(|| MonkeyError::IntegerOverflow(format!("{} + {}", total, val)))?;
+    }
+    Ok(Object::Integer(total))
+}
+
+fn abs(_: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
+    if args.len() != 1 {
+        return Err(MonkeyError::Custom(format!(
+            "wrong number of arguments. got={}, want=1",
+            args.len()
+        )));
+    }
+    match &args[0] {
+        Object::Integer(val) => val
+            .checked_abs()
+            .map(Object::Integer)
+            .ok_or_else(|| MonkeyError::IntegerOverflow(format!("abs({})", val))),
+        Object::Float(val) => Ok(Object::Float(val.abs())),
+        arg => Err(MonkeyError::Custom(format!(
+            "arg to `abs` not supported, got {}",
+            arg.obj_type()
+        ))),
+    }
+}
+
+fn pow(_: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
+    if args.len() != 2 {
+        return Err(MonkeyError::Custom(format!(
+            "wrong number of arguments. got={}, want=2",
+            args.len()
+        )));
+    }
+    match (&args[0], &args[1]) {
+        (Object::Integer(base), Object::Integer(exp)) => {
+            if *exp < 0 {
+                return Err(MonkeyError::Custom(format!(
+                    "exponent of `pow` must be non-negative, got {}",
+                    exp
+                )));
+            }
+            u32::try_from(*exp)
+                .ok()
+                .and_then(|exp| base.checked_pow(exp))
+                .map(Object::Integer)
+                .ok_or_else(|| MonkeyError::IntegerOverflow(format!("pow({}, {})", base, exp)))
+        }
+        (Object::Float(base), Object::Float(exp)) => Ok(Object::Float(base.powf(*exp))),
+        (Object::Float(base), Object::Integer(exp)) => Ok(Object::Float(base.powf(*exp as f64))),
+        (Object::Integer(base), Object::Float(exp)) => Ok(Object::Float((*base as f64).powf(*exp))),
+        (Object::Integer(_) | Object::Float(_), arg) | (arg, _) => Err(MonkeyError::Custom(
+            format!("arg to `pow` not supported, got {}", arg.obj_type()),
+        )),
+    }
+}
+
+fn sqrt(_: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
+    if args.len() != 1 {
+        return Err(MonkeyError::Custom(format!(
+            "wrong number of arguments. got={}, want=1",
+            args.len()
+        )));
+    }
+    let val = match &args[0] {
+        Object::Integer(val) => *val as f64,
+        Object::Float(val) => *val,
+        arg => {
+            return Err(MonkeyError::Custom(format!(
+                "arg to `sqrt` not supported, got {}",
+                arg.obj_type()
+            )))
+        }
+    };
+    if val < 0.0 {
+        return Err(MonkeyError::Custom(format!(
+            "cannot take `sqrt` of a negative number: {}",
+            args[0]
+        )));
+    }
+    Ok(Object::Float(val.sqrt()))
+}
+
+/// Checks that `args` is a single array of integers and returns them.
+fn integer_elements(name: &str, args: &[Object]) -> Result<Vec<i64>> {
+    if args.len() != 1 {
+        return Err(MonkeyError::Custom(format!(
+            "wrong number of arguments. got={}, want=1",
+            args.len()
+        )));
+    }
+    let elements = match &args[0] {
+        Object::Array { elements } => elements,
+        arg => {
+            return Err(MonkeyError::Custom(format!(
+                "arg to `{}` not supported, got {}",
+                name,
+                arg.obj_type()
+            )))
+        }
+    };
+    elements
+        .iter()
+        .map(|el| match el {
+            Object::Integer(val) => Ok(*val),
+            el => Err(MonkeyError::Custom(format!(
+                "elements of `{}` must be INTEGER, got {}",
+                name,
+                el.obj_type()
+            ))),
+        })
+        .collect()
+}
+
+/// Stable merge sort driven by a monkey comparator, which returns a negative, zero or
+/// positive integer. Unlike `slice::sort_by` it tolerates comparators that aren't a total
+/// order and stops at the first error raised by the comparator.
+fn merge_sort(e: &mut Evaluator, func: &Object, mut elements: Vec<Object>) -> Result<Vec<Object>> {
+    if elements.len() <= 1 {
+        return Ok(elements);
+    }
+    let right = elements.split_off(elements.len() / 2);
+    let left = merge_sort(e, func, elements)?;
+    let right = merge_sort(e, func, right)?;
+    let mut result = Vec::with_capacity(left.len() + right.len());
+    let mut left = left.into_iter().peekable();
+    let mut right = right.into_iter().peekable();
+    while let (Some(l), Some(r)) = (left.peek(), right.peek()) {
+        let ordering = match e.apply_function(func.clone(), vec![l.clone(), r.clone()])? {
+            Object::Integer(val) => val.cmp(&0),
+            obj => {
+                return Err(MonkeyError::Custom(format!(
+                    "comparator of `sort` must return INTEGER, got {}",
+                    obj.obj_type()
+                )))
+            }
+        };
+        if ordering == Ordering::Greater {
+            result.extend(right.next());
+        } else {
+            result.extend(left.next());
+        }
+    }
+    result.extend(left);
+    result.extend(right);
+    Ok(result)
+}
+
+/// `format(template, args...)` replaces each `{}` in `template` with the next argument,
+/// printed like `str` does. `{{` and `}}` stand for literal braces.
+/// The number of placeholders has to match the number of arguments.
+fn format(_: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
+    let mut args = args.into_iter();
+    let template = match args.next() {
+        Some(Object::String(template)) => template,
+        Some(arg) => {
+            return Err(MonkeyError::Custom(format!(
+                "arg to `format` not supported, got {}",
+                arg.obj_type()
+            )))
+        }
+        None => {
+            return Err(MonkeyError::Custom(
+                "wrong number of arguments. got=0, want=1 or more".to_string(),
+            ))
+        }
+    };
+    let values: Vec<Object> = args.collect();
+    let mut result = String::new();
+    let mut placeholders = 0;
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('}')) => {
+                chars.next();
+                if let Some(value) = values.get(placeholders) {
+                    result.push_str(&stringify(value));
+                }
+                placeholders += 1;
+            }
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                result.push(c);
+            }
+            _ => result.push(c),
+        }
+    }
+    if placeholders != values.len() {
+        return Err(MonkeyError::Custom(format!(
+            "`format` got {} placeholders but {} values",
+            placeholders,
+            values.len()
+        )));
+    }
+    Ok(Object::String(result))
+}
+
+fn stringify(obj: &Object) -> String {
+    match obj {
+        Object::String(val) => val.clone(),
+        obj => obj.to_string(),
+    }
+}
+
+fn is_callable(obj: &Object) -> bool {
+    matches!(obj, Object::FunctionLiteral { .. } | Object::BuiltIn(_))
+}
+
+/// `repeat(value, n)` gives an array holding `n` copies of `value`.
+/// Arrays and hashes are copied, so changing one element doesn't affect the others.
+fn repeat(_: &mut Evaluator, args: Vec<Object>) -> Result<Object> {
+    let (value, n) = match args.as_slice() {
+        [value, Object::Integer(n)] => (value, *n),
+        [_, arg] => {
+            return Err(MonkeyError::Custom(format!(
+                "arg to `repeat` not supported, got {}",
+                arg.obj_type()
+            )))
+        }
+        _ => {
+            return Err(MonkeyError::Custom(format!(
+                "wrong number of arguments. got={}, want=2",
+                args.len()
+            )))
+        }
+    };
+    if n < 0 {
+        return Err(MonkeyError::Custom(format!(
+            "count to `repeat` must not be negative, got {}",
+            n
+        )));
+    }
+    if n.saturating_mul(element_count(value)) > REPEAT_LIMIT {
+        return Err(MonkeyError::Custom(format!(
+            "repeat too large, at most {} elements are allowed",
+            REPEAT_LIMIT
+        )));
+    }
+    Ok(Object::Array {
+        elements: vec![value.clone(); n as usize],
+    })
+}
+
+/// How many elements a copy of `obj` holds, counting `obj` itself and, for arrays and
+/// hashes, everything nested in them. A hash pair counts its key and its value.
+fn element_count(obj: &Object) -> i64 {
+    match obj {
+        Object::Array { elements } => elements
+            .iter()
+            .fold(1, |count, el| count.saturating_add(element_count(el))),
+        Object::Hash { pairs } => pairs.iter().fold(1, |count, (key, value)| {
+            count
+                .saturating_add(element_count(key))
+                .saturating_add(element_count(value))
+        }),
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup() {
+        for func in BUILTIN {
+            match (lookup(func.name), &func.builtin) {
+                (Some(Object::BuiltIn(found)), Object::BuiltIn(expected)) => {
+                    assert!(std::ptr::fn_addr_eq(found, *expected), "{}", func.name)
+                }
+                other => panic!("unexpected lookup of {}: {:?}", func.name, other),
+            }
+        }
+        assert_eq!(
+            lookup("clone").map(|o| o.obj_type()).as_deref(),
+            Some("BUILTIN")
+        );
+        assert!(lookup("deep_clone").is_none());
+        assert!(lookup("nope").is_none());
+    }
+}
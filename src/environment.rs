@@ -1,10 +1,13 @@
 use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
-use crate::object::Object;
+use crate::{
+    error::{MonkeyError, Result},
+    object::Object,
+};
 
 #[derive(Debug, Clone)]
 pub struct Environment {
-    pub(crate) store: HashMap<String, Object>,
+    store: HashMap<String, Object>,
     outer: Option<Rc<RefCell<Environment>>>,
 }
 
@@ -14,6 +17,16 @@ impl Default for Environment {
     }
 }
 
+/// Builds a top-level environment holding the given bindings.
+impl FromIterator<(String, Object)> for Environment {
+    fn from_iter<I: IntoIterator<Item = (String, Object)>>(iter: I) -> Self {
+        Environment {
+            store: iter.into_iter().collect(),
+            outer: None,
+        }
+    }
+}
+
 impl Environment {
     pub fn new() -> Self {
         Environment {
@@ -21,6 +34,16 @@ impl Environment {
             outer: None,
         }
     }
+
+    /// A top-level environment seeded with `globals`, e.g. to hand to `Evaluator::from`.
+    pub fn with_globals<I, K>(globals: I) -> Self
+    where
+        I: IntoIterator<Item = (K, Object)>,
+        K: Into<String>,
+    {
+        globals.into_iter().map(|(k, v)| (k.into(), v)).collect()
+    }
+
     pub fn get(&mut self, key: String) -> Option<Object> {
         match self.store.get(&key) {
             Some(val) => Some(val.clone()),
@@ -35,10 +58,144 @@ impl Environment {
         self.store.insert(key, val);
     }
 
+    /// Updates an existing binding in the scope where it was defined,
+    /// walking the outer scopes if needed.
+    pub fn assign(&mut self, key: String, val: Object) -> Result<()> {
+        if let Some(slot) = self.store.get_mut(&key) {
+            *slot = val;
+            return Ok(());
+        }
+        match self.outer {
+            Some(ref outer) => outer.borrow_mut().assign(key, val),
+            None => Err(MonkeyError::UncaughtRef(key)),
+        }
+    }
+
+    /// Changes an existing binding in place through `f`, in the scope where it was defined.
+    pub fn update<F>(&mut self, key: &str, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut Object) -> Result<()>,
+    {
+        if let Some(slot) = self.store.get_mut(key) {
+            return f(slot);
+        }
+        match self.outer {
+            Some(ref outer) => outer.borrow_mut().update(key, f),
+            None => Err(MonkeyError::UncaughtRef(key.to_string())),
+        }
+    }
+
+    /// Iterates over the bindings of this scope only, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Object)> {
+        self.store.iter()
+    }
+
+    /// Every binding visible from this scope, walking the outer scopes.
+    /// A name bound in several scopes is listed once, with its innermost value.
+    pub fn all_bindings(&self) -> HashMap<String, Object> {
+        let mut bindings = match self.outer {
+            Some(ref outer) => outer.borrow().all_bindings(),
+            None => HashMap::new(),
+        };
+        for (k, v) in self.store.iter() {
+            bindings.insert(k.clone(), v.clone());
+        }
+        bindings
+    }
+
     pub fn new_enclosed_env(outer: Rc<RefCell<Environment>>) -> Environment {
         Environment {
             store: HashMap::new(),
             outer: Some(outer),
         }
     }
+
+    /// The scope this one is enclosed by, `None` for a top-level environment.
+    pub fn outer(&self) -> Option<Rc<RefCell<Environment>>> {
+        self.outer.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::Environment;
+    use crate::{evaluator::Evaluator, object::Object};
+
+    #[test]
+    fn test_with_globals() {
+        let env = Environment::with_globals([("x", Object::Integer(10))]);
+        let mut e = Evaluator::from(env);
+        assert_eq!(e.run("x + 5").unwrap(), Object::Integer(15));
+
+        let env: Environment = vec![
+            ("a".to_string(), Object::from(1)),
+            ("b".to_string(), Object::from("two")),
+        ]
+        .into_iter()
+        .collect();
+        let mut e = Evaluator::from(env);
+        assert_eq!(e.run("str(a) + b").unwrap(), Object::from("1two"));
+        assert_eq!(e.bindings().len(), 2);
+    }
+
+    #[test]
+    fn test_assign_outer() {
+        let outer = Rc::new(RefCell::new(Environment::new()));
+        outer.borrow_mut().set("a".to_string(), Object::Integer(1));
+        let mut inner = Environment::new_enclosed_env(Rc::clone(&outer));
+
+        inner.assign("a".to_string(), Object::Integer(2)).unwrap();
+        assert!(inner.store.is_empty());
+        assert_eq!(
+            outer.borrow_mut().get("a".to_string()),
+            Some(Object::Integer(2))
+        );
+        assert_eq!(inner.get("a".to_string()), Some(Object::Integer(2)));
+    }
+
+    #[test]
+    fn test_assign_shadowed() {
+        let outer = Rc::new(RefCell::new(Environment::new()));
+        outer.borrow_mut().set("a".to_string(), Object::Integer(1));
+        let mut inner = Environment::new_enclosed_env(Rc::clone(&outer));
+        inner.set("a".to_string(), Object::Integer(10));
+
+        inner.assign("a".to_string(), Object::Integer(20)).unwrap();
+        assert_eq!(inner.get("a".to_string()), Some(Object::Integer(20)));
+        assert_eq!(
+            outer.borrow_mut().get("a".to_string()),
+            Some(Object::Integer(1))
+        );
+    }
+
+    #[test]
+    fn test_assign_undefined() {
+        let outer = Rc::new(RefCell::new(Environment::new()));
+        let mut inner = Environment::new_enclosed_env(Rc::clone(&outer));
+        assert_eq!(
+            inner
+                .assign("a".to_string(), Object::Integer(1))
+                .unwrap_err()
+                .to_string(),
+            "Uncaught ReferenceError: a is not defined"
+        );
+        assert!(outer.borrow().store.is_empty());
+    }
+
+    #[test]
+    fn test_all_bindings() {
+        let outer = Rc::new(RefCell::new(Environment::new()));
+        outer.borrow_mut().set("a".to_string(), Object::Integer(1));
+        outer.borrow_mut().set("b".to_string(), Object::Integer(2));
+        let mut inner = Environment::new_enclosed_env(Rc::clone(&outer));
+        inner.set("b".to_string(), Object::Integer(3));
+
+        assert_eq!(inner.iter().count(), 1);
+        let all = inner.all_bindings();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all["a"], Object::Integer(1));
+        assert_eq!(all["b"], Object::Integer(3));
+    }
 }
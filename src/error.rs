@@ -2,18 +2,28 @@ use std::fmt;
 
 use crate::{
     operator::{Infix, Prefix},
-    token::Token,
+    token::{Span, Token},
 };
 
 #[derive(Debug)]
 pub enum MonkeyError {
     Custom(String),
     UnsupportedNumError,
-    UnexpectedToken(Token, Token),
+    UnexpectedToken(Token, Token, Span),
+    NoPrefixParse(Token, Span),
+    IllegalCharacter(char, Span),
     TypeMismatch(String, String, Infix),
     UnknownOperator(String, String, Infix),
     UnknownPrefix(Prefix, String),
     UncaughtRef(String),
+    UnusableHashKey(String),
+    DivisionByZero,
+    IndexOutOfRange(i64, usize),
+    IntegerOverflow(String),
+    NotCallable(String),
+    StackOverflow(usize),
+    BudgetExceeded,
+    AssertionFailed(Option<String>),
 }
 
 impl fmt::Display for MonkeyError {
@@ -24,8 +34,14 @@ impl fmt::Display for MonkeyError {
                 f,
                 "UnsupportedNumError: Monkey only supports integer numbers"
             ),
-            MonkeyError::UnexpectedToken(expected, actual) => {
-                write!(f, "expected {:?}, but got {:?}", expected, actual)
+            MonkeyError::UnexpectedToken(expected, actual, span) => {
+                write!(f, "expected {}, but got {} at {}", expected, actual, span)
+            }
+            MonkeyError::NoPrefixParse(token, span) => {
+                write!(f, "unexpected {} at {}", token, span)
+            }
+            MonkeyError::IllegalCharacter(c, span) => {
+                write!(f, "illegal character: '{}' at {}", c, span)
             }
             MonkeyError::TypeMismatch(left, right, op) => {
                 write!(f, "type mismatch: {} {} {}", left, op, right)
@@ -39,6 +55,21 @@ impl fmt::Display for MonkeyError {
             MonkeyError::UncaughtRef(ident) => {
                 write!(f, "Uncaught ReferenceError: {} is not defined", ident)
             }
+            MonkeyError::UnusableHashKey(obj_type) => {
+                write!(f, "unusable as hash key: {}", obj_type)
+            }
+            MonkeyError::DivisionByZero => write!(f, "division by zero"),
+            MonkeyError::IndexOutOfRange(index, len) => {
+                write!(f, "index out of range: {} for length {}", index, len)
+            }
+            MonkeyError::IntegerOverflow(expr) => write!(f, "integer overflow: {}", expr),
+            MonkeyError::NotCallable(obj_type) => write!(f, "not a function: {}", obj_type),
+            MonkeyError::BudgetExceeded => write!(f, "evaluation budget exceeded"),
+            MonkeyError::AssertionFailed(None) => write!(f, "assertion failed"),
+            MonkeyError::AssertionFailed(Some(msg)) => write!(f, "assertion failed: {}", msg),
+            MonkeyError::StackOverflow(depth) => {
+                write!(f, "stack overflow: more than {} nested calls", depth)
+            }
         }
     }
 }
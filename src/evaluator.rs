@@ -1,17 +1,63 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    ffi::OsStr,
+    fmt, fs,
+    io::{stdout, Write},
+    ops::ControlFlow,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
 
 use crate::{
     ast::{self, Expr},
     builtin::lookup,
     environment::Environment,
     error::{MonkeyError, Result},
-    object::Object,
+    lexer::Lexer,
+    object::{BuiltinFn, Object},
     operator::{Infix, Prefix},
+    parser::Parser,
 };
 
-#[derive(Debug)]
+/// Default for `Evaluator::max_depth`.
+pub const MAX_DEPTH: usize = 1000;
+
 pub struct Evaluator {
     pub env: Rc<RefCell<Environment>>,
+    /// Where output builtins write to, stdout by default.
+    pub out: Rc<RefCell<dyn Write>>,
+    /// How many function calls may be nested before `StackOverflow` is returned.
+    /// The thread running the evaluator needs enough stack for this many calls,
+    /// which for the default is more than the 2MiB of a spawned thread in debug builds.
+    pub max_depth: usize,
+    /// Whether scripts may call `import`, which reads files from disk.
+    /// Off by default so code from an untrusted source can't read arbitrary files.
+    pub allow_import: bool,
+    depth: usize,
+    /// How many more statements and expressions may be evaluated, unlimited when `None`.
+    budget: Option<u64>,
+    /// Builtins registered by the host, which take precedence over the static ones.
+    builtins: HashMap<String, BuiltinFn>,
+    /// Canonical paths of the files run so far, so each one is imported only once.
+    imported: HashSet<PathBuf>,
+    /// Directory of the file being run, which `import` paths are relative to.
+    current_dir: Option<PathBuf>,
+}
+
+impl fmt::Debug for Evaluator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Evaluator")
+            .field("env", &self.env)
+            .field("max_depth", &self.max_depth)
+            .field("allow_import", &self.allow_import)
+            .field("depth", &self.depth)
+            .field("budget", &self.budget)
+            .field("builtins", &self.builtins.keys())
+            .field("imported", &self.imported)
+            .field("current_dir", &self.current_dir)
+            .finish_non_exhaustive()
+    }
 }
 
 impl Default for Evaluator {
@@ -22,17 +68,71 @@ impl Default for Evaluator {
 
 impl Evaluator {
     pub fn new() -> Self {
-        Evaluator {
-            env: Rc::new(RefCell::new(Environment::new())),
-        }
+        Self::from(Environment::new())
     }
 
     pub fn from(env: Environment) -> Self {
         Evaluator {
             env: Rc::new(RefCell::new(env)),
+            out: Rc::new(RefCell::new(stdout())),
+            max_depth: MAX_DEPTH,
+            allow_import: false,
+            depth: 0,
+            budget: None,
+            builtins: HashMap::new(),
+            imported: HashSet::new(),
+            current_dir: None,
+        }
+    }
+
+    /// Creates an evaluator whose builtins write their output to `out` instead of stdout.
+    pub fn with_writer(out: Rc<RefCell<dyn Write>>) -> Self {
+        Evaluator { out, ..Self::new() }
+    }
+
+    /// Creates an evaluator that fails with `BudgetExceeded` once it has evaluated
+    /// `budget` statements and expressions.
+    pub fn with_budget(budget: u64) -> Self {
+        Evaluator {
+            budget: Some(budget),
+            ..Self::new()
+        }
+    }
+
+    /// Makes the native function `f` callable from scripts as `name`.
+    /// It shadows a static builtin of the same name.
+    pub fn register_builtin(&mut self, name: &str, f: BuiltinFn) {
+        self.builtins.insert(name.to_string(), f);
+    }
+
+    /// Looks up a builtin by name, preferring the ones registered by the host.
+    fn lookup_builtin(&self, name: &str) -> Option<Object> {
+        match self.builtins.get(name) {
+            Some(f) => Some(Object::BuiltIn(*f)),
+            None => lookup(name),
+        }
+    }
+
+    /// Accounts for the evaluation of one node against the budget.
+    fn tick(&mut self) -> Result<()> {
+        match self.budget {
+            Some(0) => Err(MonkeyError::BudgetExceeded),
+            Some(ref mut n) => {
+                *n -= 1;
+                Ok(())
+            }
+            None => Ok(()),
         }
     }
 
+    /// Writes `s` to the output sink and flushes it.
+    pub fn write_out(&mut self, s: &str) -> Result<()> {
+        let mut out = self.out.borrow_mut();
+        out.write_all(s.as_bytes())
+            .and_then(|_| out.flush())
+            .map_err(|e| MonkeyError::Custom(e.to_string()))
+    }
+
     pub fn set(&mut self, key: String, val: Object) {
         self.env.borrow_mut().set(key, val);
     }
@@ -41,38 +141,252 @@ impl Evaluator {
         self.env.borrow_mut().get(key.to_string())
     }
 
+    /// Bindings of the innermost scope, sorted by name.
+    pub fn bindings(&self) -> Vec<(String, Object)> {
+        let mut bindings: Vec<(String, Object)> = self
+            .env
+            .borrow()
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        bindings.sort_by(|a, b| a.0.cmp(&b.0));
+        bindings
+    }
+
+    /// Every binding visible from the current scope, including those of the outer scopes,
+    /// sorted by name. Shadowed names appear once with their innermost value.
+    pub fn dump_env(&self) -> Vec<(String, Object)> {
+        let mut bindings: Vec<(String, Object)> =
+            self.env.borrow().all_bindings().into_iter().collect();
+        bindings.sort_by(|a, b| a.0.cmp(&b.0));
+        bindings
+    }
+
+    /// Parses and evaluates `source` in this evaluator's environment,
+    /// so bindings persist across calls.
+    pub fn run(&mut self, source: &str) -> Result<Object> {
+        let l = Lexer::new(source);
+        let mut p = Parser::new(l);
+        let program = p.parse_program()?;
+        self.eval(program)
+    }
+
+    /// Runs the file at `path` in this evaluator's environment.
+    /// `import`s inside it are resolved relative to the file's directory.
+    pub fn run_file(&mut self, path: &Path) -> Result<Object> {
+        let path = path.canonicalize().map_err(|err| {
+            MonkeyError::Custom(format!("cannot read {}: {}", path.display(), err))
+        })?;
+        let code = fs::read_to_string(&path).map_err(|err| {
+            MonkeyError::Custom(format!("cannot read {}: {}", path.display(), err))
+        })?;
+        self.imported.insert(path.clone());
+        let dir = path.parent().map(Path::to_path_buf);
+        let outer = std::mem::replace(&mut self.current_dir, dir);
+        let result = self.run(&code);
+        self.current_dir = outer;
+        result
+    }
+
+    /// Runs the `.monkey` file at `path`, relative to the file currently being run, unless it
+    /// has been run already. Skipping known files also stops import cycles.
+    ///
+    /// The file runs in the top-level scope, so its bindings are global even when it is
+    /// imported from inside a function. Fails unless `allow_import` is set.
+    pub fn import(&mut self, path: &str) -> Result<()> {
+        if !self.allow_import {
+            return Err(MonkeyError::Custom(format!(
+                "cannot import {}: imports are disabled",
+                path
+            )));
+        }
+        let path = match &self.current_dir {
+            Some(dir) => dir.join(path),
+            None => PathBuf::from(path),
+        };
+        check_monkey_file(&path)?;
+        let canonical = path.canonicalize().map_err(|err| {
+            MonkeyError::Custom(format!("cannot import {}: {}", path.display(), err))
+        })?;
+        // a `.monkey` link may point at any file
+        check_monkey_file(&canonical)?;
+        if !self.imported.contains(&canonical) {
+            let global = self.global_env();
+            let outer = std::mem::replace(&mut self.env, global);
+            let result = self.run_file(&canonical);
+            self.env = outer;
+            result?;
+        }
+        Ok(())
+    }
+
+    /// The top-level scope the current one is nested in.
+    fn global_env(&self) -> Rc<RefCell<Environment>> {
+        let mut env = Rc::clone(&self.env);
+        loop {
+            let outer = env.borrow().outer();
+            match outer {
+                Some(outer) => env = outer,
+                None => return env,
+            }
+        }
+    }
+
     pub fn eval(&mut self, node: ast::Program) -> Result<Object> {
         let mut result = Object::Null;
         for stmt in node.stmts.iter() {
             result = self.eval_stmt(stmt)?;
-            if let Object::ReturnValue(_) = result {
-                return Ok(result);
+            match result {
+                // a top-level `return` ends the program with the unwrapped value
+                Object::ReturnValue(val) => return Ok(*val),
+                Object::Break | Object::Continue => return Err(outside_loop(&result)),
+                _ => {}
             }
         }
         Ok(result)
     }
 
     pub fn eval_stmt(&mut self, stmt: &ast::Stmt) -> Result<Object> {
+        self.tick()?;
         match stmt {
             ast::Stmt::LetStatement { ident, value } => {
                 let val = self.eval_expr(value)?;
-                self.env.borrow_mut().set(ident.to_string(), val);
+                if val.is_control_flow() {
+                    return Ok(val);
+                }
+                match ident {
+                    Expr::ArrayLiteral { elements: names } => self.destructure(names, val)?,
+                    ident => self.env.borrow_mut().set(ident.to_string(), val),
+                }
+                Ok(Object::Null)
+            }
+            ast::Stmt::AssignStatement { target, value } => {
+                let val = self.eval_expr(value)?;
+                if val.is_control_flow() {
+                    return Ok(val);
+                }
+                self.assign(target, val)?;
                 Ok(Object::Null)
             }
             ast::Stmt::ReturnStatement { value } => {
                 let right = self.eval_expr(value)?;
                 Ok(Object::ReturnValue(Box::new(right)))
             }
+            ast::Stmt::BreakStatement => Ok(Object::Break),
+            ast::Stmt::ContinueStatement => Ok(Object::Continue),
             ast::Stmt::ExpressionStatement { expr } => self.eval_expr(expr),
             ast::Stmt::BlockStatement { stmts } => self.eval_block_stmt(stmts),
+            ast::Stmt::WhileStatement { condition, body } => {
+                while self.eval_expr(condition)?.is_truthy() {
+                    match self.eval_stmt(body)? {
+                        Object::ReturnValue(val) => return Ok(Object::ReturnValue(val)),
+                        Object::Break => break,
+                        _ => {}
+                    }
+                }
+                Ok(Object::Null)
+            }
+            ast::Stmt::ForStatement {
+                init,
+                condition,
+                post,
+                body,
+            } => {
+                // the loop gets its own scope so the loop variable doesn't leak
+                let outer = Rc::clone(&self.env);
+                self.env = Rc::new(RefCell::new(Environment::new_enclosed_env(Rc::clone(
+                    &outer,
+                ))));
+                let result = self.eval_for_stmt(init, condition, post, body);
+                self.env = outer;
+                result
+            }
+        }
+    }
+
+    fn eval_for_stmt(
+        &mut self,
+        init: &ast::Stmt,
+        condition: &Expr,
+        post: &ast::Stmt,
+        body: &ast::Stmt,
+    ) -> Result<Object> {
+        // `init` and `post` are not part of the body, so a signal from them
+        // leaves this loop and goes to whatever encloses it
+        let val = self.eval_stmt(init)?;
+        if val.is_control_flow() {
+            return Ok(val);
+        }
+        while self.eval_expr(condition)?.is_truthy() {
+            match self.eval_stmt(body)? {
+                Object::ReturnValue(val) => return Ok(Object::ReturnValue(val)),
+                Object::Break => break,
+                _ => {}
+            }
+            let val = self.eval_stmt(post)?;
+            if val.is_control_flow() {
+                return Ok(val);
+            }
+        }
+        Ok(Object::Null)
+    }
+
+    /// Assigns to a variable, or to an element of one like `a[0]["k"] = val`.
+    /// Arrays and hashes are values, so only the variable named in `target` sees the change,
+    /// not other variables the collection was copied into.
+    fn assign(&mut self, target: &Expr, val: Object) -> Result<()> {
+        let mut indices = Vec::new();
+        let mut base = target;
+        while let Expr::IndexExpr { left, index } = base {
+            indices.push(self.eval_expr(index)?);
+            base = left;
+        }
+        indices.reverse();
+        match base {
+            Expr::Ident(name) => self
+                .env
+                .borrow_mut()
+                .update(name, |slot| assign_index(slot, &indices, val)),
+            _ => Err(MonkeyError::Custom(format!(
+                "invalid assignment target: {}",
+                target
+            ))),
         }
     }
 
+    /// Binds each name of a `let [a, b] = ...` pattern to the element at the same position.
+    /// The value has to be an array with exactly as many elements as there are names.
+    fn destructure(&mut self, names: &[Expr], val: Object) -> Result<()> {
+        let elements = match val {
+            Object::Array { elements } => elements,
+            val => {
+                return Err(MonkeyError::Custom(format!(
+                    "cannot destructure {} as an array",
+                    val.obj_type()
+                )))
+            }
+        };
+        if elements.len() != names.len() {
+            return Err(MonkeyError::Custom(format!(
+                "cannot destructure an array of {} elements into {} names",
+                elements.len(),
+                names.len()
+            )));
+        }
+        for (name, el) in names.iter().zip(elements) {
+            self.set(name.to_string(), el);
+        }
+        Ok(())
+    }
+
+    /// A block evaluates to the value of its final statement. Only expression statements
+    /// have a value, so a block ending in e.g. `let` or a loop, or an empty block, is `null`.
+    /// `return`, `break` and `continue` stop the block early and are passed on as they are.
     pub fn eval_block_stmt(&mut self, stmts: &[ast::Stmt]) -> Result<Object> {
         let mut result = Object::Null;
         for s in stmts.iter() {
             result = self.eval_stmt(s)?;
-            if let Object::ReturnValue(_) = result {
+            if result.is_control_flow() {
                 return Ok(result);
             }
         }
@@ -80,22 +394,54 @@ impl Evaluator {
     }
 
     pub fn eval_expr(&mut self, expr: &ast::Expr) -> Result<Object> {
+        self.tick()?;
         match expr {
             ast::Expr::Ident(ident) => match self.env.borrow_mut().get(ident.to_string()) {
                 Some(val) => Ok(val),
-                None => Err(MonkeyError::UncaughtRef(ident.to_string())),
+                None => match self.lookup_builtin(ident) {
+                    Some(builtin) => Ok(builtin),
+                    None => Err(MonkeyError::UncaughtRef(ident.to_string())),
+                },
             },
             ast::Expr::String(val) => Ok(Object::String(val.to_string())),
             ast::Expr::Int(val) => Ok(Object::Integer(*val)),
+            ast::Expr::Float(val) => Ok(Object::Float(*val)),
             ast::Expr::Boolean(val) => Ok(Object::Boolean(*val)),
+            ast::Expr::Null => Ok(Object::Null),
             ast::Expr::PrefixExpr { op, right } => {
                 let right = self.eval_expr(right)?;
                 self.eval_prefix_expr(op, right)
             }
-            ast::Expr::InfixExpr { left, right, op } => {
-                let left = self.eval_expr(left)?;
-                let right = self.eval_expr(right)?;
-                self.eval_infix_expr(left, right, op)
+            ast::Expr::InfixExpr { .. } => {
+                // a left-associative chain like `1 + 2 + ... + n` nests on its left side,
+                // so walk down that side first and fold from the innermost operand
+                // instead of recursing once per term
+                let mut chain = Vec::new();
+                let mut leftmost = expr;
+                while let ast::Expr::InfixExpr { left, right, op } = leftmost {
+                    chain.push((op, right));
+                    leftmost = left;
+                }
+                let mut acc = self.eval_expr(leftmost)?;
+                for (op, right) in chain.into_iter().rev() {
+                    // `??` only evaluates its right side when the left one is null,
+                    // `&&` and `||` only when the left one doesn't decide the result
+                    match op {
+                        Infix::Coalesce if !matches!(acc, Object::Null) => continue,
+                        Infix::And if !acc.is_truthy() => {
+                            acc = Object::Boolean(false);
+                            continue;
+                        }
+                        Infix::Or if acc.is_truthy() => {
+                            acc = Object::Boolean(true);
+                            continue;
+                        }
+                        _ => {}
+                    }
+                    let right = self.eval_expr(right)?;
+                    acc = self.eval_infix_expr(acc, right, op)?;
+                }
+                Ok(acc)
             }
             ast::Expr::IfExpr {
                 condition,
@@ -111,41 +457,57 @@ impl Evaluator {
                     }
                 }
             }
+            ast::Expr::ConditionalExpr {
+                condition,
+                consequence,
+                alternative,
+            } => {
+                // only the chosen branch is evaluated
+                if self.eval_expr(condition)?.is_truthy() {
+                    self.eval_expr(consequence)
+                } else {
+                    self.eval_expr(alternative)
+                }
+            }
             ast::Expr::FuncLiteral { parameters, body } => Ok(Object::FunctionLiteral {
                 params: parameters.to_vec(),
                 body: *body.clone(),
-                env: Environment::new_enclosed_env(Rc::clone(&self.env)),
+                env: Rc::clone(&self.env),
             }),
             ast::Expr::CallExpr { function, args } => {
-                let args = self.eval_call_expr(args.to_vec())?;
-                if let ast::Expr::Ident(func) = &**function {
-                    match lookup(func) {
-                        Some(func) => match func {
-                            Object::BuiltIn(f) => f(args),
-                            _ => todo!(),
-                        },
-                        None => {
-                            let func = self.eval_expr(function)?;
-                            self.apply_function(func, args)
-                        }
-                    }
-                } else {
-                    let func = self.eval_expr(function)?;
-                    self.apply_function(func, args)
-                }
-            }
-            Expr::ArrayLiteral { elements } => {
-                let elements = self.eval_call_expr(elements.to_vec())?;
-                Ok(Object::Array { elements })
+                let args = match self.eval_call_expr(args)? {
+                    ControlFlow::Continue(args) => args,
+                    ControlFlow::Break(flow) => return Ok(flow),
+                };
+                let func = self.eval_expr(function)?;
+                self.apply_function(func, args)
             }
+            Expr::ArrayLiteral { elements } => match self.eval_call_expr(elements)? {
+                ControlFlow::Continue(elements) => Ok(Object::Array { elements }),
+                ControlFlow::Break(flow) => Ok(flow),
+            },
             Expr::IndexExpr { left, index } => {
                 let left = self.eval_expr(left)?;
                 let index = self.eval_expr(index)?;
                 match (left, index) {
                     (Object::Array { elements }, Object::Integer(index)) => {
-                        match elements.get(index as usize) {
-                            Some(obj) => Ok(obj.clone()),
-                            None => todo!(),
+                        match resolve_index(index, elements.len()) {
+                            Some(i) => Ok(elements[i].clone()),
+                            None => Ok(Object::Null),
+                        }
+                    }
+                    (Object::String(val), Object::Integer(index)) => {
+                        let chars: Vec<char> = val.chars().collect();
+                        match resolve_index(index, chars.len()) {
+                            Some(i) => Ok(Object::String(chars[i].to_string())),
+                            None => Ok(Object::Null),
+                        }
+                    }
+                    (Object::Hash { pairs }, index) => {
+                        index.check_hashable()?;
+                        match pairs.iter().find(|(k, _)| *k == index) {
+                            Some((_, v)) => Ok(v.clone()),
+                            None => Ok(Object::Null),
                         }
                     }
                     _ => Err(MonkeyError::Custom(
@@ -153,8 +515,23 @@ impl Evaluator {
                     )),
                 }
             }
-            Expr::HashLiteral { pairs } => todo!(),
+            Expr::HashLiteral { pairs } => self.eval_hash_literal(pairs),
+        }
+    }
+
+    pub fn eval_hash_literal(&mut self, pairs: &[(Expr, Expr)]) -> Result<Object> {
+        let mut result: Vec<(Object, Object)> = Vec::new();
+        for (key, value) in pairs.iter() {
+            let key = self.eval_expr(key)?;
+            key.check_hashable()?;
+            let value = self.eval_expr(value)?;
+            // a duplicated key overwrites the previous value but keeps its position
+            match result.iter().position(|(k, _)| *k == key) {
+                Some(i) => result[i].1 = value,
+                None => result.push((key, value)),
+            }
         }
+        Ok(Object::Hash { pairs: result })
     }
 
     pub fn eval_prefix_expr(&mut self, op: &Prefix, right: Object) -> Result<Object> {
@@ -165,7 +542,11 @@ impl Evaluator {
                 _ => Ok(Object::Boolean(false)),
             },
             Prefix::Minus => match right {
-                Object::Integer(val) => Ok(Object::Integer(-val)),
+                Object::Integer(val) => match val.checked_neg() {
+                    Some(val) => Ok(Object::Integer(val)),
+                    None => Err(MonkeyError::IntegerOverflow(format!("-({})", val))),
+                },
+                Object::Float(val) => Ok(Object::Float(-val)),
                 _ => Err(MonkeyError::UnknownPrefix(
                     op.clone(),
                     "BOOLEAN".to_string(),
@@ -175,17 +556,47 @@ impl Evaluator {
     }
 
     pub fn eval_infix_expr(&mut self, left: Object, right: Object, op: &Infix) -> Result<Object> {
+        // these work on any operands, so they are settled before looking at the types
+        match op {
+            Infix::Coalesce if left == Object::Null => return Ok(right),
+            Infix::Coalesce => return Ok(left),
+            Infix::And => return Ok(Object::Boolean(left.is_truthy() && right.is_truthy())),
+            Infix::Or => return Ok(Object::Boolean(left.is_truthy() || right.is_truthy())),
+            _ => {}
+        }
         match (left, right) {
             (Object::Integer(left), Object::Integer(right)) => match op {
-                Infix::Plus => Ok(Object::Integer(left + right)),
-                Infix::Minus => Ok(Object::Integer(left - right)),
-                Infix::Asterisk => Ok(Object::Integer(left * right)),
-                Infix::Slash => Ok(Object::Integer(left / right)),
-                Infix::Gt => Ok(Object::Boolean(left < right)),
-                Infix::Lt => Ok(Object::Boolean(left > right)),
+                Infix::Plus => checked_int(left.checked_add(right), left, right, op),
+                Infix::Minus => checked_int(left.checked_sub(right), left, right, op),
+                Infix::Asterisk => checked_int(left.checked_mul(right), left, right, op),
+                Infix::Slash => {
+                    if right == 0 {
+                        return Err(MonkeyError::DivisionByZero);
+                    }
+                    checked_int(left.checked_div(right), left, right, op)
+                }
+                Infix::Lt => Ok(Object::Boolean(left < right)),
+                Infix::Gt => Ok(Object::Boolean(left > right)),
+                Infix::Le => Ok(Object::Boolean(left <= right)),
+                Infix::Ge => Ok(Object::Boolean(left >= right)),
                 Infix::Eq => Ok(Object::Boolean(left == right)),
                 Infix::NotEq => Ok(Object::Boolean(left != right)),
+                _ => Err(MonkeyError::UnknownOperator(
+                    "INTEGER".to_string(),
+                    "INTEGER".to_string(),
+                    op.clone(),
+                )),
             },
+            (Object::Float(left), Object::Float(right)) => {
+                self.eval_float_infix_expr(left, right, op)
+            }
+            // an integer operand is promoted to float when mixed with a float
+            (Object::Integer(left), Object::Float(right)) => {
+                self.eval_float_infix_expr(left as f64, right, op)
+            }
+            (Object::Float(left), Object::Integer(right)) => {
+                self.eval_float_infix_expr(left, right as f64, op)
+            }
             (Object::Boolean(left), Object::Boolean(right)) => match op {
                 Infix::Eq => Ok(Object::Boolean(left == right)),
                 Infix::NotEq => Ok(Object::Boolean(left != right)),
@@ -197,12 +608,24 @@ impl Evaluator {
             },
             (Object::String(left), Object::String(right)) => match op {
                 Infix::Plus => Ok(Object::String(format!("{}{}", left, right))),
+                Infix::Eq => Ok(Object::Boolean(left == right)),
+                Infix::NotEq => Ok(Object::Boolean(left != right)),
                 _ => Err(MonkeyError::UnknownOperator(
                     "STRING".to_string(),
                     "STRING".to_string(),
                     op.clone(),
                 )),
             },
+            // `+` with a single string operand joins it with the other operand's display form;
+            // the string itself is used as is, without quotes
+            (Object::String(left), right) if *op == Infix::Plus => {
+                Ok(Object::String(format!("{}{}", left, right)))
+            }
+            (left, Object::String(right)) if *op == Infix::Plus => {
+                Ok(Object::String(format!("{}{}", left, right)))
+            }
+            (left, right) if *op == Infix::Eq => Ok(Object::Boolean(left == right)),
+            (left, right) if *op == Infix::NotEq => Ok(Object::Boolean(left != right)),
             (left, right) => Err(MonkeyError::TypeMismatch(
                 left.obj_type(),
                 right.obj_type(),
@@ -211,39 +634,209 @@ impl Evaluator {
         }
     }
 
-    pub fn eval_call_expr(&mut self, params: Vec<Expr>) -> Result<Vec<Object>> {
-        let mut result: Vec<Object> = Vec::new();
+    pub fn eval_float_infix_expr(&mut self, left: f64, right: f64, op: &Infix) -> Result<Object> {
+        match op {
+            Infix::Plus => Ok(Object::Float(left + right)),
+            Infix::Minus => Ok(Object::Float(left - right)),
+            Infix::Asterisk => Ok(Object::Float(left * right)),
+            Infix::Slash => Ok(Object::Float(left / right)),
+            Infix::Lt => Ok(Object::Boolean(left < right)),
+            Infix::Gt => Ok(Object::Boolean(left > right)),
+            Infix::Le => Ok(Object::Boolean(left <= right)),
+            Infix::Ge => Ok(Object::Boolean(left >= right)),
+            Infix::Eq => Ok(Object::Boolean(left == right)),
+            Infix::NotEq => Ok(Object::Boolean(left != right)),
+            _ => Err(MonkeyError::UnknownOperator(
+                "FLOAT".to_string(),
+                "FLOAT".to_string(),
+                op.clone(),
+            )),
+        }
+    }
+
+    /// Evaluates arguments or array elements in order. A `return`, `break` or `continue`
+    /// in one of them stops the evaluation and is passed back as `ControlFlow::Break`.
+    pub fn eval_call_expr(&mut self, params: &[Expr]) -> Result<ControlFlow<Object, Vec<Object>>> {
+        let mut result: Vec<Object> = Vec::with_capacity(params.len());
         for p in params.iter() {
             let evaluated = self.eval_expr(p)?;
+            if evaluated.is_control_flow() {
+                return Ok(ControlFlow::Break(evaluated));
+            }
             result.push(evaluated);
         }
-        Ok(result)
+        Ok(ControlFlow::Continue(result))
     }
 
+    /// Calls a user-defined function or a builtin.
+    ///
+    /// Each call evaluates the body in a fresh scope enclosed by the scope the function
+    /// was defined in. That scope is shared, not copied, so a closure sees later changes
+    /// to the variables it captured, and every closure created by the same call shares them.
     pub fn apply_function(&mut self, function: Object, args: Vec<Object>) -> Result<Object> {
         if let Object::FunctionLiteral { params, body, env } = function {
-            let mut env = Evaluator::from(env);
-            for (ident, arg) in params.iter().zip(args.iter()) {
+            if params.len() != args.len() {
+                return Err(MonkeyError::Custom(format!(
+                    "wrong number of arguments. got={}, want={}",
+                    args.len(),
+                    params.len()
+                )));
+            }
+            if self.depth >= self.max_depth {
+                return Err(MonkeyError::StackOverflow(self.max_depth));
+            }
+            // the body runs in a scope enclosed by the one the function captured
+            let enclosed = Rc::new(RefCell::new(Environment::new_enclosed_env(env)));
+            let outer = std::mem::replace(&mut self.env, enclosed);
+            for (ident, arg) in params.into_iter().zip(args) {
                 if let ast::Expr::Ident(ident) = ident {
-                    env.set(ident.to_owned(), arg.clone())
+                    self.set(ident, arg)
                 }
             }
-            match env.eval_stmt(&body) {
+            self.depth += 1;
+            let result = self.eval_stmt(&body);
+            self.depth -= 1;
+            self.env = outer;
+            match result {
                 Ok(Object::ReturnValue(val)) => Ok(*val),
+                Ok(obj @ (Object::Break | Object::Continue)) => Err(outside_loop(&obj)),
                 obj => obj,
             }
+        } else if let Object::BuiltIn(f) = function {
+            f(self, args)
         } else {
-            todo!();
+            Err(MonkeyError::NotCallable(function.obj_type()))
+        }
+    }
+}
+
+fn check_monkey_file(path: &Path) -> Result<()> {
+    match path.extension().and_then(OsStr::to_str) {
+        Some("monkey") => Ok(()),
+        _ => Err(MonkeyError::Custom(format!(
+            "cannot import {}: only .monkey files can be imported",
+            path.display()
+        ))),
+    }
+}
+
+/// Stores `val` at the element of `slot` reached through `indices`, or in `slot` itself
+/// when there are none. Array indices have to exist, while hashes get missing keys added.
+fn assign_index(slot: &mut Object, indices: &[Object], val: Object) -> Result<()> {
+    let Some((index, rest)) = indices.split_first() else {
+        *slot = val;
+        return Ok(());
+    };
+    match (slot, index) {
+        (Object::Array { elements }, Object::Integer(i)) => {
+            let len = elements.len();
+            let pos = resolve_index(*i, len).ok_or(MonkeyError::IndexOutOfRange(*i, len))?;
+            assign_index(&mut elements[pos], rest, val)
+        }
+        (Object::Hash { pairs }, key) => {
+            key.check_hashable()?;
+            match pairs.iter_mut().find(|(k, _)| k == key) {
+                Some((_, v)) => assign_index(v, rest, val),
+                None if rest.is_empty() => {
+                    pairs.push((key.clone(), val));
+                    Ok(())
+                }
+                // the missing value reads as null, which can't be indexed into
+                None => assign_index(&mut Object::Null, rest, val),
+            }
         }
+        (slot, index) => Err(MonkeyError::Custom(format!(
+            "index assignment not supported: {}[{}]",
+            slot.obj_type(),
+            index.obj_type()
+        ))),
+    }
+}
+
+fn outside_loop(signal: &Object) -> MonkeyError {
+    MonkeyError::Custom(format!("`{}` outside of a loop", signal))
+}
+
+/// Turns a possibly negative index into a position in a collection of `len` items.
+/// Negative indices count from the end, so `-1` is the last item.
+/// Returns `None` when the index is out of range either way.
+pub(crate) fn resolve_index(index: i64, len: usize) -> Option<usize> {
+    let len = i64::try_from(len).ok()?;
+    let index = if index < 0 { index + len } else { index };
+    if (0..len).contains(&index) {
+        Some(index as usize)
+    } else {
+        None
+    }
+}
+
+fn checked_int(result: Option<i64>, left: i64, right: i64, op: &Infix) -> Result<Object> {
+    match result {
+        Some(val) => Ok(Object::Integer(val)),
+        None => Err(MonkeyError::IntegerOverflow(format!(
+            "{} {} {}",
+            left, op, right
+        ))),
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{lexer::Lexer, parser::Parser};
+    use std::{cell::RefCell, rc::Rc};
+
+    use crate::{lexer::Lexer, object::Object, parser::Parser};
 
     use super::Evaluator;
 
+    /// Evaluates each input in a fresh evaluator and compares the printed result,
+    /// or the error message if evaluation fails, with the expected string.
+    fn assert_eval(case: &[(&str, &str)]) {
+        for (input, expected) in case.iter() {
+            let r = match eval(Evaluator::new(), input) {
+                Ok(r) => r.to_string(),
+                Err(e) => e.to_string(),
+            };
+            assert_eq!(r, *expected, "{}", input);
+        }
+    }
+
+    /// Like `assert_eval`, but evaluation must succeed.
+    fn assert_eval_ok(case: &[(&str, &str)]) {
+        for (input, expected) in case.iter() {
+            let r = eval(Evaluator::new(), input).unwrap();
+            assert_eq!(r.to_string(), *expected, "{}", input);
+        }
+    }
+
+    /// Like `assert_eval`, but evaluation must fail.
+    fn assert_eval_err(case: &[(&str, &str)]) {
+        for (input, expected) in case.iter() {
+            let e = eval(Evaluator::new(), input).unwrap_err();
+            assert_eq!(e.to_string(), *expected, "{}", input);
+        }
+    }
+
+    /// Like `assert_eval`, but also compares what the input wrote to the output.
+    fn assert_eval_output(case: &[(&str, &str, &str)]) {
+        for (input, expected, output) in case.iter() {
+            let out = Rc::new(RefCell::new(Vec::new()));
+            let r = match eval(Evaluator::with_writer(out.clone()), input) {
+                Ok(r) => r.to_string(),
+                Err(e) => e.to_string(),
+            };
+            assert_eq!(r, *expected, "{}", input);
+            assert_eq!(String::from_utf8(out.borrow().clone()).unwrap(), *output);
+        }
+    }
+
+    /// Evaluates `input`, which must parse.
+    fn eval(mut e: Evaluator, input: &str) -> crate::error::Result<Object> {
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program().unwrap();
+        e.eval(program)
+    }
+
     #[test]
     fn test_integer_ope() {
         let case = [
@@ -254,6 +847,11 @@ mod tests {
             ("5 + 5 + 5 + 5 - 10", "10"),
             ("2 * 2 * 2 * 2 * 2", "32"),
             ("-50 + 100 - 50", "0"),
+            ("--5", "5"),
+            ("5 - -5", "10"),
+            ("-(5)", "-5"),
+            ("let a = 2; let b = 3; -a * -b", "6"),
+            ("-(1 + 2) * 3", "-9"),
             ("5 * 2 + 10", "20"),
             ("5 + 2 * 10", "25"),
             ("50 / 2 * 2 + 10", "60"),
@@ -263,14 +861,44 @@ mod tests {
             ("(5 + 10 * 2 + 15 / 3) * 2 + -10", "50"),
         ];
 
-        for (input, expected) in case.iter() {
-            let mut e = Evaluator::new();
-            let l = Lexer::new(input);
-            let mut p = Parser::new(l);
-            let program = p.parse_program().unwrap();
-            let r = e.eval(program).unwrap();
-            assert_eq!(r.to_string(), *expected)
-        }
+        assert_eval_ok(&case);
+    }
+
+    #[test]
+    fn test_float_ope() {
+        let case = [
+            ("1.25", "1.25"),
+            ("-2.5", "-2.5"),
+            ("1.25 * 2", "2.5"),
+            ("1.5 + 1.5", "3.0"),
+            ("5.", "5.0"),
+            ("0.5 - 1", "-0.5"),
+            ("1 / 0.5", "2.0"),
+            ("1.5 < 2", "true"),
+            ("2.0 == 2", "true"),
+            ("2.5 != 2.5", "false"),
+            ("1.5", "1.5"),
+            ("3.0", "3.0"),
+            ("7 / 2", "3"),
+            ("7.0 / 2", "3.5"),
+            ("6.0 / 2", "3.0"),
+            ("-7 / 2", "-3"),
+        ];
+        assert_eval_ok(&case);
+
+        let mut e = Evaluator::new();
+        assert_eq!(e.run("1 + 0.5").unwrap().obj_type(), "FLOAT");
+    }
+
+    #[test]
+    fn test_run() {
+        let mut e = Evaluator::new();
+        assert_eq!(e.run("let a = 5;").unwrap().to_string(), "null");
+        assert_eq!(e.run("let b = a * 2; b + a").unwrap().to_string(), "15");
+        assert_eq!(
+            e.run("c").unwrap_err().to_string(),
+            "Uncaught ReferenceError: c is not defined"
+        );
     }
 
     #[test]
@@ -282,14 +910,7 @@ mod tests {
             ("!!false", "false"),
             ("!!5", "true"),
         ];
-        for (input, expected) in case.iter() {
-            let mut e = Evaluator::new();
-            let l = Lexer::new(input);
-            let mut p = Parser::new(l);
-            let program = p.parse_program().unwrap();
-            let r = e.eval(program).unwrap();
-            assert_eq!(r.to_string(), *expected);
-        }
+        assert_eval_ok(&case);
     }
 
     #[test]
@@ -299,6 +920,12 @@ mod tests {
             ("1 > 2", "false"),
             ("1 < 1", "false"),
             ("1 > 1", "false"),
+            ("5 <= 5", "true"),
+            ("4 >= 5", "false"),
+            ("6 >= 5", "true"),
+            ("1 + 1 <= 2", "true"),
+            ("2 * 3 >= 7 == false", "true"),
+            ("1.5 <= 2", "true"),
             ("1 == 1", "true"),
             ("1 != 1", "false"),
             ("1 == 2", "false"),
@@ -309,16 +936,27 @@ mod tests {
             ("(1 < 2) == false", "false"),
             ("(1 > 2) == true", "false"),
             ("(1 > 2) == false", "true"),
+            (r#""a" == "a""#, "true"),
+            (r#""a" != "b""#, "true"),
+            ("[1, 2] == [1, 2]", "true"),
+            ("[1] != [2]", "true"),
+            ("[1, [2]] == [1, [2]]", "true"),
+            ("[1, 2] == [1]", "false"),
+            (r#"{"a": 1, "b": 2} == {"b": 2, "a": 1}"#, "true"),
+            ("null", "null"),
+            ("null == null", "true"),
+            ("null != 1", "true"),
+            ("null == false", "false"),
+            ("!null", "true"),
+            ("let x = null; x == null", "true"),
+            ("if (false) { 1 } == null", "true"),
+            ("let n = if (false) { 1 }; n == n", "true"),
+            ("let n = if (false) { 1 }; n != 1", "true"),
+            ("1 == true", "false"),
+            ("let f = fn(){ 1 }; f == f", "false"),
         ];
 
-        for (input, expected) in case {
-            let mut e = Evaluator::new();
-            let l = Lexer::new(input);
-            let mut p = Parser::new(l);
-            let program = p.parse_program().unwrap();
-            let r = e.eval(program).unwrap();
-            assert_eq!(r.to_string(), expected)
-        }
+        assert_eval_ok(&case);
     }
 
     #[test]
@@ -326,194 +964,615 @@ mod tests {
         let case = [
             (r#""foobar""#, r#""foobar""#),
             (r#""Hello" + " " + "World""#, r#""Hello World""#),
+            (r#""n=" + 5"#, r#""n=5""#),
+            (r#"true + "!""#, r#""true!""#),
+            (r#""xs: " + [1, "a"]"#, r#""xs: [1, "a"]""#),
+            (r#"1 + 2 + "3""#, r#""33""#),
+            (r#""abc"[1]"#, r#""b""#),
+            (r#""abc"[-1]"#, r#""c""#),
+            (r#""abc"[3]"#, "null"),
+            (r#""abc"[-4]"#, "null"),
+            // indexing gives a one-character string, not a separate char type
+            (r#""abc"[0]"#, r#""a""#),
+            (r#""a" == "abc"[0]"#, "true"),
+            (r#"type("abc"[0])"#, r#""STRING""#),
+            (r#""héllo"[1]"#, r#""é""#),
+            (r#""abc"[0] + "abc"[2]"#, r#""ac""#),
+            (r#"chars("abc")[1] == "abc"[1]"#, "true"),
         ];
-        for (input, expected) in case.iter() {
-            let mut e = Evaluator::new();
-            let l = Lexer::new(input);
-            let mut p = Parser::new(l);
-            let program = p.parse_program().unwrap();
-            let r = e.eval(program).unwrap();
-            assert_eq!(r.to_string(), *expected)
-        }
+        assert_eval_ok(&case);
     }
 
     #[test]
     fn test_array() {
-        let case = [("[1, 2 * 2, 3 + 3]", "[1, 4, 6]")];
-        for (input, expected) in case.iter() {
-            let mut e = Evaluator::new();
-            let l = Lexer::new(input);
-            let mut p = Parser::new(l);
-            let program = p.parse_program().unwrap();
-            let r = e.eval(program).unwrap();
-            assert_eq!(r.to_string(), *expected)
-        }
-    }
-
-    #[test]
-    fn test_if_else_expr() {
-        let case = [("if(true){10}", "10"), ("if (false) { 10 }", "null")];
-        for (input, expected) in case.iter() {
-            let mut e = Evaluator::new();
-            let l = Lexer::new(input);
-            let mut p = Parser::new(l);
-            let program = p.parse_program().unwrap();
-            let r = e.eval(program).unwrap();
-            assert_eq!(r.to_string(), *expected)
-        }
+        let case = [
+            ("[1, 2 * 2, 3 + 3]", "[1, 4, 6]"),
+            ("[1, 2, 3][0]", "1"),
+            ("[1, 2, 3][1 + 1]", "3"),
+            ("[1, 2, 3][5]", "null"),
+            ("[1, 2, 3][-1]", "3"),
+            ("[1, 2, 3][-3]", "1"),
+            ("[1, 2, 3][-4]", "null"),
+            ("[][0]", "null"),
+            ("[][-1]", "null"),
+        ];
+        assert_eval_ok(&case);
     }
 
     #[test]
-    fn test_return_stmt() {
+    fn test_null_coalescing() {
         let case = [
-            ("return 10", "10"),
-            ("return 2 * 5", "10"),
+            ("null ?? 5", "5"),
+            ("3 ?? 5", "3"),
+            ("false ?? 5", "false"),
+            ("null ?? null", "null"),
+            ("null ?? null ?? 1", "1"),
+            ("let h = {\"a\": 1}; h[\"b\"] ?? 0", "0"),
+            ("1 ?? undefined_name", "1"),
+            ("let n = 0; let f = fn() { n = n + 1 }; 1 ?? f(); n", "0"),
             (
-                "if (10 > 1) { if (10 > 1) {
-            return 10; }
-            return 1; }",
-                "10",
+                "null ?? undefined_name",
+                "Uncaught ReferenceError: undefined_name is not defined",
             ),
         ];
-        for (input, expected) in case.iter() {
-            let mut e = Evaluator::new();
-            let l = Lexer::new(input);
-            let mut p = Parser::new(l);
-            let program = p.parse_program().unwrap();
-            let r = e.eval(program).unwrap();
-            assert_eq!(r.to_string(), *expected)
-        }
+        assert_eval(&case);
     }
 
     #[test]
-    fn test_error() {
+    fn test_logical_operators() {
         let case = [
-            ("5 + true", "type mismatch: INTEGER + BOOLEAN"),
-            ("5 + true; 5;", "type mismatch: INTEGER + BOOLEAN"),
-            ("-true", "unknown prefix: -BOOLEAN"),
-            ("true + false", "unknown operator: BOOLEAN + BOOLEAN"),
-            ("5; true + false; 5", "unknown operator: BOOLEAN + BOOLEAN"),
+            ("true && true", "true"),
+            ("true && false", "false"),
+            ("false || true", "true"),
+            ("false || false", "false"),
+            ("true and false", "false"),
+            ("false or true", "true"),
+            ("not true", "false"),
+            ("not null", "true"),
+            ("1 && \"a\"", "true"),
+            ("1 && null", "false"),
+            ("null || 0", "true"),
+            ("1 < 2 && 2 < 3", "true"),
+            ("false && undefined_name", "false"),
+            ("true || undefined_name", "true"),
             (
-                "if(10 > 1) { true + false; }",
-                "unknown operator: BOOLEAN + BOOLEAN",
+                "let n = 0; let f = fn() { n = n + 1; true }; false && f(); true || f(); n",
+                "0",
             ),
             (
-                "if (10 > 1) { if (10 > 1) { return true + false} return 1;}",
-                "unknown operator: BOOLEAN + BOOLEAN",
+                "true && undefined_name",
+                "Uncaught ReferenceError: undefined_name is not defined",
             ),
-            ("foobar", "Uncaught ReferenceError: foobar is not defined"),
-            (r#""Hello" - "World""#, "unknown operator: STRING - STRING"),
         ];
-        for (input, expected) in case.iter() {
-            let mut e = Evaluator::new();
-            let l = Lexer::new(input);
-            let mut p = Parser::new(l);
-            let program = p.parse_program().unwrap();
-            let r = e.eval(program);
-            assert_eq!(r.unwrap_err().to_string(), *expected);
-        }
+        assert_eval(&case);
     }
+
     #[test]
-    fn test_let_statement() {
+    fn test_boolean_results() {
         let case = [
-            ("let a = 5; a;", "5"),
-            ("let a = 5 * 5; a;", "25"),
-            ("let a = 5; let b = a; b;", "5"),
-            ("let a = 5; let b = a; let c = a + b + 5; c;", "15"),
+            ("1 < 2", Object::Boolean(true)),
+            ("1 > 2", Object::Boolean(false)),
+            ("!false", Object::Boolean(true)),
+            ("!!1", Object::Boolean(true)),
+            ("true == (1 < 2)", Object::Boolean(true)),
+            ("(1 < 2) == (2 < 3)", Object::Boolean(true)),
+            ("(1 < 2) != (2 > 3)", Object::Boolean(true)),
+            (
+                "[1 < 2, 1 > 2]",
+                Object::from(vec![true.into(), false.into()]),
+            ),
+            ("if (false) { 1 }", Object::Null),
         ];
         for (input, expected) in case.iter() {
             let mut e = Evaluator::new();
-            let l = Lexer::new(input);
-            let mut p = Parser::new(l);
-            let program = p.parse_program().unwrap();
-            let r = e.eval(program).unwrap();
-            assert_eq!(r.to_string(), *expected)
+            let r = e.run(input).unwrap();
+            assert_eq!(r.to_string(), expected.to_string());
+            assert_eq!(r, *expected);
         }
+        assert_eq!(Object::Boolean(true).to_string(), "true");
+        assert_eq!(Object::Boolean(false).to_string(), "false");
+        assert_eq!(Object::Null.to_string(), "null");
     }
+
     #[test]
-    fn test_function_literal() {
+    fn test_conditional_expr() {
         let case = [
-            ("let identity = fn(x){ x; }; identity(5);", "5"),
-            ("let identity = fn(x){ return x; }; identity(5);", "5"),
-            ("let double = fn(x) { x * 2; }; double(5);", "10"),
-            ("let add = fn(x, y){ x + y;}; add(5, 5);", "10"),
-            ("let add = fn(x, y){ x + y;}; add(5 + 5, add(5, 5));", "20"),
-            // (
-            //     "let fibonacci = fn(x) {
-            //         if (x == 0) {
-            //             0;
-            //         }
-            //         else {
-            //             if (x == 1) { 1 }
-            //             else {fibonacci(x - 1) + fibonacci(x - 2); }
-            //         }
-            //     };
-            //     let foo = fibonacci(2);
-            //     ",
-            //     "2",
-            // ),
-            // (
-            //     "let add = fn(x){ let inner = fn(y){ if(y == 0){y}; inner(y - 1)};}; add(5);",
-            //     "2",
-            // ),
-            // FIXME: parser
-            // ("fn(x) { x }(5)", "5"),
+            ("true ? 1 : 2", "1"),
+            ("false ? 1 : 2", "2"),
+            ("1 > 2 ? 1 : 2", "2"),
+            (
+                "null_value ? 1 : 2",
+                "Uncaught ReferenceError: null_value is not defined",
+            ),
+            ("let n = 5; n < 0 ? -1 : n == 0 ? 0 : 1", "1"),
+            ("true ? 1 : undefined_name", "1"),
+            ("false ? undefined_name : 2", "2"),
+            ("let a = [1]; true ? len(a) : a[0]", "1"),
         ];
-        for (input, expected) in case.iter() {
-            let mut e = Evaluator::new();
-            let l = Lexer::new(input);
-            let mut p = Parser::new(l);
-            let program = p.parse_program().unwrap();
-            let r = e.eval(program).unwrap();
-            assert_eq!(r.to_string(), *expected)
-        }
+        assert_eval(&case);
     }
+
     #[test]
-    fn test_builtin_string_len() {
+    fn test_let_destructuring() {
         let case = [
-            (r#"len("")"#, "0"),
-            (r#"len("four")"#, "4"),
-            (r#"len("hello world")"#, "11"),
-            (r#"len(1)"#, "arg to `len` not supported, got INTEGER"),
+            ("let [a, b, c] = [1, 2, 3]; a + b * c", "7"),
+            ("let [a, b] = [[1], \"x\"]; b", "\"x\""),
+            ("let f = fn() { [1, 2] }; let [x, y] = f(); x - y", "-1"),
+            ("let [] = []; 1", "1"),
+            ("let a = 1; let [a] = [2]; a", "2"),
             (
-                r#"len("one", "two")"#,
-                "wrong number of arguments. got=2, want=1",
+                "let [a, b] = [1, 2, 3]",
+                "cannot destructure an array of 3 elements into 2 names",
+            ),
+            (
+                "let [a, b, c] = [1]",
+                "cannot destructure an array of 1 elements into 3 names",
             ),
+            ("let [a] = 1", "cannot destructure INTEGER as an array"),
         ];
-        for (input, expected) in case.iter() {
-            let mut e = Evaluator::new();
-            let l = Lexer::new(input);
-            let mut p = Parser::new(l);
-            let program = p.parse_program().unwrap();
-            match e.eval(program) {
-                Ok(r) => assert_eq!(r.to_string(), *expected),
-                Err(e) => assert_eq!(e.to_string(), *expected),
-            }
-        }
+        assert_eval(&case);
     }
 
     #[test]
-    fn test_builtin_array_len() {
+    fn test_if_else_expr() {
         let case = [
-            (r#"len([])"#, "0"),
-            (r#"len([1,2,3,4])"#, "4"),
-            (r#"len(["1","2","3","4"])"#, "4"),
-            (r#"len([1,"2",3,"4"])"#, "4"),
-            (
-                r#"len(["one"], ["two"])"#,
-                "wrong number of arguments. got=2, want=1",
+            ("if(true){10}", "10"),
+            ("if (false) { 10 }", "null"),
+            ("let x = if (true) { 1 + 1 }; x", "2"),
+            ("let x = if (false) { 1 + 1 }; x", "null"),
+            ("let x = if (1 > 2) { 1 } else { 2 }; x", "2"),
+            ("if (true) { 1; 2; 3 }", "3"),
+            ("if (true) { 1; let y = 2; }", "null"),
+            ("if (true) { 1; while (false) {} }", "null"),
+            ("if (true) {}", "null"),
+            ("if (false) { 1 } else {}", "null"),
+        ];
+        assert_eval_ok(&case);
+    }
+
+    #[test]
+    fn test_return_stmt() {
+        let case = [
+            ("return 10", "10"),
+            ("return 2 * 5", "10"),
+            (
+                "if (10 > 1) { if (10 > 1) {
+            return 10; }
+            return 1; }",
+                "10",
+            ),
+            ("let x = if (true) { return 5; }; 99", "5"),
+            ("let x = 1; x = if (true) { return 5; }; 99", "5"),
+            (
+                "let f = fn() { let x = if (true) { return 5; }; 99 }; f()",
+                "5",
             ),
+            ("let f = fn() { [1, if (true) { return 5; }, 3] }; f()", "5"),
+            ("let f = fn() { len(if (true) { return 5; }) }; f()", "5"),
         ];
-        for (input, expected) in case.iter() {
-            let mut e = Evaluator::new();
-            let l = Lexer::new(input);
-            let mut p = Parser::new(l);
-            let program = p.parse_program().unwrap();
-            match e.eval(program) {
-                Ok(r) => assert_eq!(r.to_string(), *expected),
-                Err(e) => assert_eq!(e.to_string(), *expected),
+        assert_eval_ok(&case);
+    }
+
+    #[test]
+    fn test_while_stmt() {
+        let case = [
+            ("while (false) { 1 }", "null"),
+            (
+                "let i = 0; let sum = 0; while (i < 5) { let i = i + 1; let sum = sum + i; } sum",
+                "15",
+            ),
+            (
+                "let f = fn() { let i = 0; while (true) { let i = i + 1; if (i == 3) { return i; } } }; f()",
+                "3",
+            ),
+        ];
+        assert_eval_ok(&case);
+    }
+
+    #[test]
+    fn test_for_stmt() {
+        let case = [
+            (
+                "let sum = 0; for (let i = 0; i < 10; i = i + 1) { sum = sum + i; } sum",
+                "45",
+            ),
+            ("for (let i = 0; false; i = i + 1) { 1 }", "null"),
+            (
+                "for (let i = 0; i < 10; i = i + 1) { 1 } i",
+                "Uncaught ReferenceError: i is not defined",
+            ),
+            (
+                "let i = 100; for (let i = 0; i < 3; i = i + 1) { 1 } i",
+                "100",
+            ),
+            (
+                "let f = fn() { for (let i = 0; true; i = i + 1) { if (i == 4) { return i; } } }; f()",
+                "4",
+            ),
+            (
+                "let f = fn() { for (let i = if (true) { return 5; }; true; i = i + 1) { 1 } 9 }; f()",
+                "5",
+            ),
+            (
+                "let f = fn() { for (let i = 0; true; i = if (i == 2) { return i; } else { i + 1 }) { 1 } 9 }; f()",
+                "2",
+            ),
+            (
+                "let n = 0; while (n < 3) { n = n + 1; for (let i = if (true) { break; }; true; i = i + 1) { 1 } } n",
+                "1",
+            ),
+        ];
+        assert_eval(&case);
+    }
+
+    #[test]
+    fn test_break_continue() {
+        let case = [
+            (
+                "let i = 0; while (true) { i = i + 1; if (i == 5) { break; } } i",
+                "5",
+            ),
+            (
+                "let i = 0; let sum = 0; while (i < 5) { i = i + 1; if (i == 2) { continue; } sum = sum + i; } sum",
+                "13",
+            ),
+            (
+                "let sum = 0; for (let i = 0; i < 10; i = i + 1) { if (3 < i) { break; } sum = sum + i; } sum",
+                "6",
+            ),
+            (
+                "let sum = 0; for (let i = 0; i < 5; i = i + 1) { if (i == 1) { continue; } sum = sum + i; } sum",
+                "9",
+            ),
+            (
+                "let n = 0; while (n < 3) { n = n + 1; while (true) { break; } } n",
+                "3",
+            ),
+            (
+                "let i = 0; while (i < 3) { let x = if (true) { break; }; i = i + 1; } i",
+                "0",
+            ),
+            (
+                "let i = 0; let x = 0; while (i < 3) { i = i + 1; x = if (i == 2) { break; } else { i }; } x",
+                "1",
+            ),
+            (
+                "let i = 0; let sum = 0; while (i < 3) { i = i + 1; let n = len([1, if (i == 2) { continue; }]); sum = sum + n; } sum",
+                "4",
+            ),
+            (
+                "let i = 0; while (i < 3) { i = i + 1; puts(if (true) { break; }); } i",
+                "1",
+            ),
+            ("break", "`break` outside of a loop"),
+            (
+                "let f = fn() { continue; }; while (true) { f(); }",
+                "`continue` outside of a loop",
+            ),
+        ];
+        assert_eval(&case);
+    }
+
+    #[test]
+    fn test_error() {
+        let case = [
+            ("5 + true", "type mismatch: INTEGER + BOOLEAN"),
+            ("5 + true; 5;", "type mismatch: INTEGER + BOOLEAN"),
+            ("let x = 5; x(1)", "not a function: INTEGER"),
+            (r#""abc"()"#, "not a function: STRING"),
+            (r#"len("ab")(1)"#, "not a function: INTEGER"),
+            ("map([1], 2)", "arg to `map` not supported, got INTEGER"),
+            (r#""a" - 1"#, "type mismatch: STRING - INTEGER"),
+            (r#"2 * "b""#, "type mismatch: INTEGER * STRING"),
+            ("-true", "unknown prefix: -BOOLEAN"),
+            ("true + false", "unknown operator: BOOLEAN + BOOLEAN"),
+            ("5; true + false; 5", "unknown operator: BOOLEAN + BOOLEAN"),
+            (
+                "if(10 > 1) { true + false; }",
+                "unknown operator: BOOLEAN + BOOLEAN",
+            ),
+            (
+                "if (10 > 1) { if (10 > 1) { return true + false} return 1;}",
+                "unknown operator: BOOLEAN + BOOLEAN",
+            ),
+            ("foobar", "Uncaught ReferenceError: foobar is not defined"),
+            (r#""Hello" - "World""#, "unknown operator: STRING - STRING"),
+            ("5 / 0", "division by zero"),
+            (
+                "9223372036854775807 + 1",
+                "integer overflow: 9223372036854775807 + 1",
+            ),
+            (
+                "-9223372036854775807 - 2",
+                "integer overflow: -9223372036854775807 - 2",
+            ),
+            (
+                "let big = 3037000500; big * big",
+                "integer overflow: 3037000500 * 3037000500",
+            ),
+            (
+                "let min = -9223372036854775807 - 1; -min",
+                "integer overflow: -(-9223372036854775808)",
+            ),
+            (
+                "let min = -9223372036854775807 - 1; min / -1",
+                "integer overflow: -9223372036854775808 / -1",
+            ),
+            ("let a = 0; 10 / a", "division by zero"),
+        ];
+        assert_eval_err(&case);
+    }
+    #[test]
+    fn test_let_statement() {
+        let case = [
+            ("let a = 5; a;", "5"),
+            ("let a = 5 * 5; a;", "25"),
+            ("let a = 5; let b = a; b;", "5"),
+            ("let a = 5; let b = a; let c = a + b + 5; c;", "15"),
+        ];
+        assert_eval_ok(&case);
+    }
+    #[test]
+    fn test_assign_statement() {
+        let case = [
+            ("let a = 5; a = 10; a", "10"),
+            ("let a = 5; a = a * 2; a", "10"),
+            ("let i = 0; while (i < 3) { i = i + 1; } i", "3"),
+            (
+                "let count = 0; let inc = fn() { count = count + 1; }; inc(); inc(); count",
+                "2",
+            ),
+            (
+                "let i = 0; let f = fn() { while (i < 3) { i = i + 1; } }; f(); i",
+                "3",
+            ),
+            (
+                "let a = 1; let f = fn() { let a = 2; a = 3; a }; f() + a",
+                "4",
+            ),
+            ("b = 1", "Uncaught ReferenceError: b is not defined"),
+        ];
+        assert_eval(&case);
+    }
+
+    #[test]
+    fn test_function_literal() {
+        let case = [
+            ("let identity = fn(x){ x; }; identity(5);", "5"),
+            ("let identity = fn(x){ return x; }; identity(5);", "5"),
+            ("let double = fn(x) { x * 2; }; double(5);", "10"),
+            ("let add = fn(x, y){ x + y;}; add(5, 5);", "10"),
+            ("let add = fn(x, y){ x + y;}; add(5 + 5, add(5, 5));", "20"),
+            ("fn(x) { x }(5)", "5"),
+            ("fn(x, y) { x * y }(2, 3)", "6"),
+            ("let a = fn() { fn(x) { x + 1 } }()(1); a", "2"),
+        ];
+        assert_eval_ok(&case);
+    }
+
+    #[test]
+    fn test_recursive_function() {
+        // a function sees its own `let` binding because it captures the scope, not a copy
+        let case = [
+            (
+                "let fact = fn(n) { if (n < 2) { 1 } else { n * fact(n - 1) } }; fact(5)",
+                "120",
+            ),
+            (
+                "let fibonacci = fn(x) {
+                    if (x == 0) {
+                        0;
+                    } else {
+                        if (x == 1) { 1 } else { fibonacci(x - 1) + fibonacci(x - 2); }
+                    }
+                };
+                [fibonacci(2), fibonacci(10)]",
+                "[1, 55]",
+            ),
+            (
+                "let count = fn(x) { let inner = fn(y) { if (y == 0) { return 0; } 1 + inner(y - 1) }; inner(x) }; count(5)",
+                "5",
+            ),
+        ];
+        assert_eval_ok(&case);
+    }
+
+    #[test]
+    fn test_mutual_recursion() {
+        // both functions only look each other up when called, by which time both are bound
+        let case = [
+            (
+                "let isEven = fn(n) { if (n == 0) { true } else { isOdd(n - 1) } };
+                let isOdd = fn(n) { if (n == 0) { false } else { isEven(n - 1) } };
+                [isEven(0), isEven(4), isEven(7), isOdd(7), isOdd(10)]",
+                "[true, true, false, true, false]",
+            ),
+            (
+                "let check = fn(x) {
+                    let isEven = fn(n) { n == 0 ? true : isOdd(n - 1) };
+                    let isOdd = fn(n) { n == 0 ? false : isEven(n - 1) };
+                    isEven(x)
+                };
+                [check(3), check(6)]",
+                "[false, true]",
+            ),
+            (
+                "let isEven = fn(n) { if (n == 0) { true } else { isOdd(n - 1) } }; isEven(1)",
+                "Uncaught ReferenceError: isOdd is not defined",
+            ),
+        ];
+        assert_eval(&case);
+    }
+
+    #[test]
+    fn test_index_assignment() {
+        let case = [
+            ("let a = [1, 2, 3]; a[1] = 9; a", "[1, 9, 3]"),
+            ("let a = [1, 2, 3]; a[-1] = 9; a", "[1, 2, 9]"),
+            (r#"let h = {"a": 1}; h["a"] = 2; h"#, r#"{"a": 2}"#),
+            (r#"let h = {"a": 1}; h["b"] = 2; h"#, r#"{"a": 1, "b": 2}"#),
+            (
+                "let m = [[1, 2], [3, 4]]; m[1][0] = 5; m",
+                "[[1, 2], [5, 4]]",
+            ),
+            (
+                r#"let h = {"xs": [1]}; h["xs"][0] = 2; h"#,
+                r#"{"xs": [2]}"#,
+            ),
+            (
+                "let a = [0, 0]; let i = 0; while (i < 2) { a[i] = i + 1; i = i + 1; } a",
+                "[1, 2]",
+            ),
+            ("let a = [0]; let f = fn() { a[0] = 1; }; f(); a", "[1]"),
+            // collections are values, so a copy doesn't see the change
+            ("let a = [1]; let b = a; b[0] = 2; a", "[1]"),
+            (
+                "let a = [1]; a[1] = 2",
+                "index out of range: 1 for length 1",
+            ),
+            (
+                "let a = [1]; a[-2] = 2",
+                "index out of range: -2 for length 1",
+            ),
+            (
+                r#"let s = "abc"; s[0] = "x""#,
+                "index assignment not supported: STRING[INTEGER]",
+            ),
+            (
+                r#"let a = [1]; a["0"] = 2"#,
+                "index assignment not supported: ARRAY[STRING]",
+            ),
+            (
+                r#"let h = {}; h["a"]["b"] = 1"#,
+                "index assignment not supported: NULL[STRING]",
+            ),
+            ("let h = {}; h[[1]] = 1", "unusable as hash key: ARRAY"),
+            ("b[0] = 1", "Uncaught ReferenceError: b is not defined"),
+        ];
+        assert_eval(&case);
+    }
+
+    #[test]
+    fn test_closure() {
+        let case = [
+            (
+                "let newAdder = fn(x) { fn(y) { x + y } }; let addTwo = newAdder(2); addTwo(3)",
+                "5",
+            ),
+            (
+                "let newAdder = fn(x) { fn(y) { x + y } }; let a = newAdder(1); let b = newAdder(10); a(1) + b(1)",
+                "13",
+            ),
+            (
+                "let newCounter = fn() { let count = 0; fn() { count } }; let counter = newCounter(); counter()",
+                "0",
+            ),
+            ("let x = 1; let f = fn() { x }; let x = 2; f()", "2"),
+        ];
+        assert_eval_ok(&case);
+    }
+
+    #[test]
+    fn test_closure_shares_captured_scope() {
+        let case = [
+            (
+                "let newCounter = fn() { let count = 0; [fn() { count = count + 1; count }, fn() { count }] }; let c = newCounter(); c[0](); c[0](); c[1]()",
+                "2",
+            ),
+            (
+                "let newCounter = fn() { let count = 0; fn() { count = count + 1; count } }; let a = newCounter(); let b = newCounter(); a(); a(); b()",
+                "1",
+            ),
+        ];
+        assert_eval_ok(&case);
+
+        // closures made in the same scope all point at it instead of each copying `big`
+        let mut e = Evaluator::new();
+        e.run(
+            "let big = range(100000); let fs = []; let i = 0; while (i < 1000) { fs = push(fs, fn() { len(big) }); i = i + 1; }",
+        )
+        .unwrap();
+        let fs = match e.get("fs") {
+            Some(Object::Array { elements }) => elements,
+            other => panic!("expected an array, got {:?}", other),
+        };
+        assert_eq!(fs.len(), 1000);
+        for f in fs {
+            match f {
+                Object::FunctionLiteral { env, .. } => assert!(Rc::ptr_eq(&env, &e.env)),
+                other => panic!("expected a function, got {:?}", other),
             }
         }
+        assert_eq!(e.run("fs[999]()").unwrap().to_string(), "100000");
+    }
+
+    #[test]
+    fn test_large_argument() {
+        let mut e = Evaluator::new();
+        let r = e
+            .run("let id = fn(x) { x }; let big = range(100000); let a = id(id(big)); [len(a), a[0], a[-1]]")
+            .unwrap();
+        assert_eq!(r.to_string(), "[100000, 0, 99999]");
+    }
+
+    #[test]
+    fn test_long_infix_chain() {
+        let terms = 100_000;
+        let input = vec!["1"; terms].join(" + ");
+        let mut e = Evaluator::new();
+        assert_eq!(e.run(&input).unwrap().to_string(), terms.to_string());
+        // still folded from the left
+        let input = format!("{} - {}", terms, vec!["1"; terms - 1].join(" - "));
+        assert_eq!(e.run(&input).unwrap().to_string(), "1");
+    }
+
+    #[test]
+    fn test_function_arity() {
+        let case = [
+            (
+                "let add = fn(x, y){ x + y }; add(5)",
+                "wrong number of arguments. got=1, want=2",
+            ),
+            (
+                "let add = fn(x, y){ x + y }; add(1, 2, 3)",
+                "wrong number of arguments. got=3, want=2",
+            ),
+            (
+                "let f = fn(){ 1 }; f(1)",
+                "wrong number of arguments. got=1, want=0",
+            ),
+        ];
+        assert_eval_err(&case);
+    }
+
+    #[test]
+    fn test_builtin_string_len() {
+        let case = [
+            (r#"len("")"#, "0"),
+            (r#"len("four")"#, "4"),
+            (r#"len("hello world")"#, "11"),
+            (r#"len(1)"#, "arg to `len` not supported, got INTEGER"),
+            (
+                r#"len("one", "two")"#,
+                "wrong number of arguments. got=2, want=1",
+            ),
+        ];
+        assert_eval(&case);
+    }
+
+    #[test]
+    fn test_builtin_array_len() {
+        let case = [
+            (r#"len([])"#, "0"),
+            (r#"len([1,2,3,4])"#, "4"),
+            (r#"len(["1","2","3","4"])"#, "4"),
+            (r#"len([1,"2",3,"4"])"#, "4"),
+            (
+                r#"len(["one"], ["two"])"#,
+                "wrong number of arguments. got=2, want=1",
+            ),
+        ];
+        assert_eval(&case);
     }
 
     #[test]
@@ -522,21 +1581,15 @@ mod tests {
             (r#"first([])"#, "this array is empty"),
             (r#"first([1,2,3,4])"#, "1"),
             (r#"first(["1","2","3","4"])"#, r#""1""#),
+            (r#"first("abc")"#, r#""a""#),
+            (r#"first("")"#, "this string is empty"),
+            ("first(1)", "arg to `first` not supported, got INTEGER"),
             (
                 r#"first(["one"], ["two"])"#,
                 "wrong number of arguments. got=2, want=1",
             ),
         ];
-        for (input, expected) in case.iter() {
-            let mut e = Evaluator::new();
-            let l = Lexer::new(input);
-            let mut p = Parser::new(l);
-            let program = p.parse_program().unwrap();
-            match e.eval(program) {
-                Ok(r) => assert_eq!(r.to_string(), *expected),
-                Err(e) => assert_eq!(e.to_string(), *expected),
-            }
-        }
+        assert_eval(&case);
     }
 
     #[test]
@@ -545,21 +1598,14 @@ mod tests {
             (r#"last([])"#, "this array is empty"),
             (r#"last([1,2,3,4])"#, "4"),
             (r#"last(["1","2","3","4"])"#, r#""4""#),
+            (r#"last("abc")"#, r#""c""#),
+            (r#"last("")"#, "this string is empty"),
             (
                 r#"last(["one"], ["two"])"#,
                 "wrong number of arguments. got=2, want=1",
             ),
         ];
-        for (input, expected) in case.iter() {
-            let mut e = Evaluator::new();
-            let l = Lexer::new(input);
-            let mut p = Parser::new(l);
-            let program = p.parse_program().unwrap();
-            match e.eval(program) {
-                Ok(r) => assert_eq!(r.to_string(), *expected),
-                Err(e) => assert_eq!(e.to_string(), *expected),
-            }
-        }
+        assert_eval(&case);
     }
 
     #[test]
@@ -568,21 +1614,16 @@ mod tests {
             (r#"rest([])"#, "this array is empty"),
             (r#"rest([1,2,3,4])"#, "[2, 3, 4]"),
             (r#"rest(["1","2","3","4"])"#, r#"["2", "3", "4"]"#),
+            (r#"rest("abc")"#, r#""bc""#),
+            (r#"rest("a")"#, r#""""#),
+            (r#"rest("")"#, "this string is empty"),
+            ("rest(1)", "arg to `rest` not supported, got INTEGER"),
             (
                 r#"rest(["one"], ["two"])"#,
                 "wrong number of arguments. got=2, want=1",
             ),
         ];
-        for (input, expected) in case.iter() {
-            let mut e = Evaluator::new();
-            let l = Lexer::new(input);
-            let mut p = Parser::new(l);
-            let program = p.parse_program().unwrap();
-            match e.eval(program) {
-                Ok(r) => assert_eq!(r.to_string(), *expected),
-                Err(e) => assert_eq!(e.to_string(), *expected),
-            }
-        }
+        assert_eval(&case);
     }
 
     #[test]
@@ -603,49 +1644,776 @@ mod tests {
                 "arg to `push` not supported, got STRING",
             ),
         ];
-        for (input, expected) in case.iter() {
-            let mut e = Evaluator::new();
-            let l = Lexer::new(input);
-            let mut p = Parser::new(l);
-            let program = p.parse_program().unwrap();
-            match e.eval(program) {
-                Ok(r) => assert_eq!(r.to_string(), *expected),
-                Err(e) => assert_eq!(e.to_string(), *expected),
-            }
+        assert_eval(&case);
+    }
+
+    #[test]
+    fn test_hash() {
+        let case = [
+            (r#"{"one": 1, "two": 2}"#, r#"{"one": 1, "two": 2}"#),
+            (r#"{"a": 1, "b": 2, "c": 3}"#, r#"{"a": 1, "b": 2, "c": 3}"#),
+            (
+                r#"{"z": 1, 10: 2, "m": 3, false: 4, "a": 5}"#,
+                r#"{"z": 1, 10: 2, "m": 3, false: 4, "a": 5}"#,
+            ),
+            (r#"{"a": 1}["a"]"#, "1"),
+            (r#"let key = "b"; {"a": 1, "b": 2}[key]"#, "2"),
+            (r#"{1: "one", true: "yes"}[1]"#, r#""one""#),
+            (r#"{1: "one", true: "yes"}[true]"#, r#""yes""#),
+            ("{true: 1}[true]", "1"),
+            ("{true: 1}[false]", "null"),
+            (r#"{1: "a", "1": "b"}["1"]"#, r#""b""#),
+            (r#"{"a": 1, "a": 2}"#, r#"{"a": 2}"#),
+            (r#"{"a": 1}["b"]"#, "null"),
+            (r#"{}["a"]"#, "null"),
+            (r#"{"a": {"b": 1 + 1}}["a"]["b"]"#, "2"),
+            (r#"{"a": 1}[[1]]"#, "unusable as hash key: ARRAY"),
+            (r#"{[1]: 1}"#, "unusable as hash key: ARRAY"),
+            (
+                r#"let f = fn(x){ x }; {f: 1}"#,
+                "unusable as hash key: FUNCTION",
+            ),
+        ];
+        assert_eval(&case);
+    }
+
+    #[test]
+    fn test_builtin_puts() {
+        let case = [
+            (r#"puts("hi")"#, "null", "\"hi\"\n"),
+            (r#"puts(1); puts("a")"#, "null", "1\n\"a\"\n"),
+            ("puts(1, [2])", "null", "1\n[2]\n"),
+            ("puts()", "null", ""),
+        ];
+        assert_eval_output(&case);
+        let out = Rc::new(RefCell::new(Vec::new()));
+        let mut e = Evaluator::with_writer(out.clone());
+        e.run(r#"let greet = fn() { puts("hi") }; greet()"#)
+            .unwrap();
+        assert!(String::from_utf8(out.borrow().clone())
+            .unwrap()
+            .contains("hi"));
+    }
+
+    #[test]
+    fn test_builtin_ident() {
+        let case = [
+            ("len", "builtin function"),
+            ("let f = len; f", "builtin function"),
+            (r#"let f = len; f("abc")"#, "3"),
+            ("[first, last]", "[builtin function, builtin function]"),
+        ];
+        assert_eval_ok(&case);
+    }
+
+    #[test]
+    fn test_builtin_shadowing() {
+        let case = [
+            ("let set = fn(x) { x * 2 }; set(21)", "42"),
+            (
+                r#"let format = fn(x) { "<" + x + ">" }; format("a")"#,
+                r#""<a>""#,
+            ),
+            ("let f = fn(each) { each(1) }; f(fn(x) { x + 1 })", "2"),
+            ("let len = 5; len", "5"),
+            ("let len = 5; len([1])", "not a function: INTEGER"),
+        ];
+        assert_eval(&case);
+    }
+
+    #[test]
+    fn test_builtin_map() {
+        let case = [
+            ("map([1, 2, 3], fn(x){ x * 2 })", "[2, 4, 6]"),
+            ("map([], fn(x){ x * 2 })", "[]"),
+            (
+                "let double = fn(x){ x * 2 }; map(map([1, 2], double), double)",
+                "[4, 8]",
+            ),
+            (
+                r#"map("abc", fn(x){ x })"#,
+                "arg to `map` not supported, got STRING",
+            ),
+            ("map([1, 2], 1)", "arg to `map` not supported, got INTEGER"),
+            ("map([1, 2])", "wrong number of arguments. got=1, want=2"),
+        ];
+        assert_eval(&case);
+    }
+
+    #[test]
+    fn test_builtin_filter() {
+        let case = [
+            ("filter([1, 2, 3, 4], fn(x){ x > 2 })", "[3, 4]"),
+            ("filter([], fn(x){ true })", "[]"),
+            ("filter([1, 2, 3], fn(x){ if (x == 2) { 1 } })", "[2]"),
+            (
+                "filter(1, fn(x){ true })",
+                "arg to `filter` not supported, got INTEGER",
+            ),
+            (
+                "filter([1], [1])",
+                "arg to `filter` not supported, got ARRAY",
+            ),
+        ];
+        assert_eval(&case);
+    }
+
+    #[test]
+    fn test_builtin_reduce() {
+        let case = [
+            ("reduce([1, 2, 3, 4], 0, fn(a, b){ a + b })", "10"),
+            ("reduce([], 7, fn(a, b){ a + b })", "7"),
+            (
+                "reduce([1, 2, 3], [], fn(acc, x){ push(acc, x * x) })",
+                "[1, 4, 9]",
+            ),
+            (
+                "reduce(1, 0, fn(a, b){ a + b })",
+                "arg to `reduce` not supported, got INTEGER",
+            ),
+            (
+                "reduce([1], 0, 0)",
+                "arg to `reduce` not supported, got INTEGER",
+            ),
+            (
+                "reduce([1], fn(a, b){ a + b })",
+                "wrong number of arguments. got=2, want=3",
+            ),
+        ];
+        assert_eval(&case);
+    }
+
+    #[test]
+    fn test_builtin_str() {
+        let case = [
+            ("str(42)", r#""42""#),
+            ("str(true)", r#""true""#),
+            ("str([1, 2])", r#""[1, 2]""#),
+            (r#"str("a")"#, r#""a""#),
+            (r#""count: " + str(1 + 2)"#, r#""count: 3""#),
+            ("str(1, 2)", "wrong number of arguments. got=2, want=1"),
+        ];
+        assert_eval(&case);
+    }
+
+    #[test]
+    fn test_builtin_split() {
+        let case = [
+            (r#"split("a,b,c", ",")"#, r#"["a", "b", "c"]"#),
+            (r#"split("a, b", ", ")"#, r#"["a", "b"]"#),
+            (r#"split("abc", "")"#, r#"["a", "b", "c"]"#),
+            (r#"split("", ",")"#, "[]"),
+            (r#"split("a,", ",")"#, r#"["a", ""]"#),
+            (
+                r#"split(1, ",")"#,
+                "arg to `split` not supported, got INTEGER",
+            ),
+            (
+                r#"split("a", 1)"#,
+                "arg to `split` not supported, got INTEGER",
+            ),
+            (r#"split("a")"#, "wrong number of arguments. got=1, want=2"),
+        ];
+        assert_eval(&case);
+    }
+
+    #[test]
+    fn test_builtin_join() {
+        let case = [
+            (r#"join(["a", "b", "c"], "-")"#, r#""a-b-c""#),
+            (r#"join([1, true, "x"], ", ")"#, r#""1, true, x""#),
+            (r#"join([], "-")"#, r#""""#),
+            (r#"join(split("a,b", ","), "")"#, r#""ab""#),
+            (
+                r#"join("abc", "-")"#,
+                "arg to `join` not supported, got STRING",
+            ),
+            (
+                r#"join(["a"], 1)"#,
+                "arg to `join` not supported, got INTEGER",
+            ),
+            (r#"join(["a"])"#, "wrong number of arguments. got=1, want=2"),
+        ];
+        assert_eval(&case);
+    }
+
+    #[test]
+    fn test_builtin_type() {
+        let case = [
+            ("type(1)", r#""INTEGER""#),
+            (r#"type("a")"#, r#""STRING""#),
+            ("type(true)", r#""BOOLEAN""#),
+            ("type([])", r#""ARRAY""#),
+            ("type({})", r#""HASH""#),
+            ("type(if (false) { 1 })", r#""NULL""#),
+            ("type(null)", r#""NULL""#),
+            ("type(1.5)", r#""FLOAT""#),
+            ("type(fn(){})", r#""FUNCTION""#),
+            ("let f = fn() { return 1 }; type(f())", r#""INTEGER""#),
+            ("type(len)", r#""BUILTIN""#),
+            ("type()", "wrong number of arguments. got=0, want=1"),
+        ];
+        assert_eval(&case);
+    }
+
+    #[test]
+    fn test_register_builtin() {
+        fn double(_: &mut Evaluator, args: Vec<Object>) -> crate::error::Result<Object> {
+            let val = i64::try_from(args.into_iter().next().unwrap_or(Object::Null))?;
+            Ok(Object::Integer(val * 2))
         }
+        fn fake_len(_: &mut Evaluator, _: Vec<Object>) -> crate::error::Result<Object> {
+            Ok(Object::from("len"))
+        }
+
+        let mut e = Evaluator::new();
+        e.register_builtin("double", double);
+        assert_eq!(e.run("double(21)").unwrap().to_string(), "42");
+        assert_eq!(e.run("map([1, 2], double)").unwrap().to_string(), "[2, 4]");
+        assert_eq!(
+            e.run(r#"double("a")"#).unwrap_err().to_string(),
+            "expected INTEGER, got STRING"
+        );
+        // a registered builtin shadows the static one
+        e.register_builtin("len", fake_len);
+        assert_eq!(e.run("len([1])").unwrap().to_string(), r#""len""#);
+        assert!(Evaluator::new().run("double(1)").is_err());
+    }
+
+    #[test]
+    fn test_dump_env() {
+        let mut e = Evaluator::new();
+        e.run(r#"let a = 1; let b = "x";"#).unwrap();
+        let names: Vec<String> = e.dump_env().into_iter().map(|(k, _)| k).collect();
+        assert_eq!(names, ["a", "b"]);
+        assert_eq!(e.dump_env(), e.bindings());
+
+        // inside a call the outer bindings are visible too
+        fn dump(e: &mut Evaluator, _: Vec<Object>) -> crate::error::Result<Object> {
+            let names = e
+                .dump_env()
+                .into_iter()
+                .map(|(k, v)| Object::from(format!("{}={}", k, v)));
+            Ok(Object::from(names.collect::<Vec<_>>()))
+        }
+        e.register_builtin("dump", dump);
+        let r = e.run("let f = fn(b) { dump() }; f(2)").unwrap();
+        assert_eq!(r.to_string(), r#"["a=1", "b=2", "f=fn(b){dump()}"]"#);
+    }
+
+    #[test]
+    fn test_budget() {
+        let mut e = Evaluator::with_budget(1000);
+        let r = e.run("while (true) {}");
+        assert_eq!(r.unwrap_err().to_string(), "evaluation budget exceeded");
+
+        let mut e = Evaluator::with_budget(1000);
+        assert_eq!(e.run("1 + 2").unwrap().to_string(), "3");
+        // `1 + 2` is a statement and three expressions
+        let mut e = Evaluator::with_budget(3);
+        assert!(e.run("1 + 2").is_err());
+        let mut e = Evaluator::with_budget(4);
+        assert!(e.run("1 + 2").is_ok());
+        assert!(e.run("1").is_err());
+    }
+
+    #[test]
+    fn test_stack_overflow() {
+        std::thread::Builder::new()
+            .stack_size(crate::STACK_SIZE)
+            .spawn(stack_overflow)
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+
+    fn stack_overflow() {
+        let mut e = Evaluator::new();
+        let r = e.run("let f = fn(n) { f(n + 1) }; f(0)");
+        assert_eq!(
+            r.unwrap_err().to_string(),
+            "stack overflow: more than 1000 nested calls"
+        );
+        // the depth is unwound after the error, so the evaluator is still usable
+        let r = e.run("let g = fn(n) { if (n == 0) { 0 } else { g(n - 1) } }; g(500)");
+        assert_eq!(r.unwrap().to_string(), "0");
+
+        e.max_depth = 10;
+        let r = e.run("g(20)");
+        assert_eq!(
+            r.unwrap_err().to_string(),
+            "stack overflow: more than 10 nested calls"
+        );
+        assert_eq!(e.run("g(9)").unwrap().to_string(), "0");
+    }
+
+    #[test]
+    fn test_return_value_type() {
+        let obj = Object::ReturnValue(Box::new(Object::Integer(1)));
+        assert_eq!(obj.obj_type(), "INTEGER");
+
+        let mut e = Evaluator::new();
+        let r = e.run("return 5; 10").unwrap();
+        assert!(matches!(r, Object::Integer(5)));
+        assert_eq!(r.obj_type(), "INTEGER");
+        assert_eq!(r.to_string(), "5");
+
+        let r = e.run("if (true) { return [1]; } 2").unwrap();
+        assert!(matches!(r, Object::Array { .. }));
+    }
+
+    #[test]
+    fn test_builtin_range() {
+        let case = [
+            ("range(3)", "[0, 1, 2]"),
+            ("range(2, 5)", "[2, 3, 4]"),
+            ("range(-2, 1)", "[-2, -1, 0]"),
+            ("range(0)", "[]"),
+            ("range(5, 2)", "[]"),
+            ("len(range(10000))", "10000"),
+            (
+                "range(100000000)",
+                "range too large, at most 10000000 elements are allowed",
+            ),
+            (
+                "range(-9223372036854775807, 9223372036854775807)",
+                "range too large, at most 10000000 elements are allowed",
+            ),
+            (r#"range("a")"#, "arg to `range` not supported, got STRING"),
+            (
+                r#"range(1, "a")"#,
+                "arg to `range` not supported, got STRING",
+            ),
+            ("range()", "wrong number of arguments. got=0, want=1 or 2"),
+        ];
+        assert_eval(&case);
+    }
+
+    #[test]
+    fn test_builtin_hash() {
+        let case = [
+            (r#"keys({"b": 1, "a": 2, 3: 3})"#, r#"["b", "a", 3]"#),
+            (r#"values({"b": 1, "a": 2, 3: 3})"#, "[1, 2, 3]"),
+            (r#"keys({"a": 1, "b": 2, "a": 3})"#, r#"["a", "b"]"#),
+            ("keys({})", "[]"),
+            (r#"delete({"a": 1, "b": 2}, "a")"#, r#"{"b": 2}"#),
+            (r#"delete({"a": 1}, "z")"#, r#"{"a": 1}"#),
+            (r#"let h = {"a": 1}; delete(h, "a"); h"#, r#"{"a": 1}"#),
+            ("keys([1])", "arg to `keys` not supported, got ARRAY"),
+            ("values(1)", "arg to `values` not supported, got INTEGER"),
+            (
+                r#"delete("a", 1)"#,
+                "arg to `delete` not supported, got STRING",
+            ),
+            (r#"delete({"a": 1}, [1])"#, "unusable as hash key: ARRAY"),
+            ("keys()", "wrong number of arguments. got=0, want=1"),
+            ("delete({})", "wrong number of arguments. got=1, want=2"),
+        ];
+        assert_eval(&case);
+    }
+
+    #[test]
+    fn test_builtin_clone() {
+        let case = [
+            ("clone(1)", "1"),
+            (r#"clone("a")"#, r#""a""#),
+            ("let a = [1, [2]]; clone(a) == a", "true"),
+            ("let a = [1, 2]; let b = push(clone(a), 3); a", "[1, 2]"),
+            (
+                "let a = [1, [2]]; let b = push(clone(a)[1], 3); a",
+                "[1, [2]]",
+            ),
+            (
+                "let a = [1, [2]]; let b = clone(a); b = push(b[1], 3); a",
+                "[1, [2]]",
+            ),
+            (
+                r#"let h = {"a": [1]}; let c = delete(clone(h), "a"); h"#,
+                r#"{"a": [1]}"#,
+            ),
+            ("clone(1, 2)", "wrong number of arguments. got=2, want=1"),
+        ];
+        assert_eval(&case);
     }
 
-    // TODO: fix parser
-    // #[test]
-    // fn test_map() {
-    //     let case = [(
-    //         r#"
-    //         let map = fn(array, f) {
-    //             let iter = fn(arr, accumulated) {
-    //                 if(len(arr) == 0){
-    //                     accumulated
-    //                 } else {
-    //                     iter(rest(arr), push(accumulated, f(first(arr))))
-    //                 }
-    //             };
-
-    //             iter(array, []);
-    //         };
-
-    //         let double = fn(x){ return x * 2 };
-    //         map([1, 2, 3, 4], double);
-    //         "#,
-    //         "[2, 4, 6, 8]",
-    //     )];
-    //     for (input, expected) in case.iter() {
-    //         let mut e = Evaluator::new();
-    //         let l = Lexer::new(input);
-    //         let mut p = Parser::new(l);
-    //         let program = p.parse_program().unwrap();
-    //         match e.eval(program) {
-    //             Ok(r) => assert_eq!(r.to_string(), *expected),
-    //             Err(e) => assert_eq!(e.to_string(), *expected),
-    //         }
-    //     }
-    // }
+    #[test]
+    fn test_builtin_sort() {
+        let case = [
+            ("sort([3, 1, 2])", "[1, 2, 3]"),
+            (r#"sort(["b", "c", "a"])"#, r#"["a", "b", "c"]"#),
+            ("sort([])", "[]"),
+            ("let a = [2, 1]; let b = sort(a); a", "[2, 1]"),
+            ("sort([3, 1, 2], fn(a, b) { b - a })", "[3, 2, 1]"),
+            (
+                r#"sort(["ccc", "a", "bb"], fn(a, b) { len(a) - len(b) })"#,
+                r#"["a", "bb", "ccc"]"#,
+            ),
+            (
+                "sort([[2, 1], [1, 2], [2, 0]], fn(a, b) { first(a) - first(b) })",
+                "[[1, 2], [2, 1], [2, 0]]",
+            ),
+            ("sort([2, 1], fn(a, b) { 0 })", "[2, 1]"),
+            (
+                r#"sort([1, "a"])"#,
+                "cannot sort an array of mixed types: INTEGER and STRING",
+            ),
+            ("sort([true, false])", "cannot sort an array of BOOLEAN"),
+            ("sort(1)", "arg to `sort` not supported, got INTEGER"),
+            ("sort([1], 1)", "arg to `sort` not supported, got INTEGER"),
+            (
+                "sort([1, 2], fn(a, b) { true })",
+                "comparator of `sort` must return INTEGER, got BOOLEAN",
+            ),
+            ("sort()", "wrong number of arguments. got=0, want=1 or 2"),
+        ];
+        assert_eval(&case);
+    }
+
+    #[test]
+    fn test_builtin_reverse() {
+        let case = [
+            ("reverse([1, 2, 3])", "[3, 2, 1]"),
+            ("reverse([])", "[]"),
+            ("let a = [1, 2]; let b = reverse(a); a", "[1, 2]"),
+            (r#"reverse("abc")"#, r#""cba""#),
+            (r#"reverse("")"#, r#""""#),
+            (r#"reverse("añb")"#, r#""bña""#),
+            ("reverse(1)", "arg to `reverse` not supported, got INTEGER"),
+            (
+                "reverse([], [])",
+                "wrong number of arguments. got=2, want=1",
+            ),
+        ];
+        assert_eval(&case);
+    }
+
+    #[test]
+    fn test_builtin_min_max_sum() {
+        let case = [
+            ("sum([1, 2, 3])", "6"),
+            ("sum([])", "0"),
+            ("sum([-1, 1])", "0"),
+            ("min([3, 1, 2])", "1"),
+            ("max([3, 1, 2])", "3"),
+            ("min([-5])", "-5"),
+            ("min([])", "cannot take `min` of an empty array"),
+            ("max([])", "cannot take `max` of an empty array"),
+            (
+                r#"sum([1, "a"])"#,
+                "elements of `sum` must be INTEGER, got STRING",
+            ),
+            (
+                "max([1, 2.5])",
+                "elements of `max` must be INTEGER, got FLOAT",
+            ),
+            ("min(1)", "arg to `min` not supported, got INTEGER"),
+            ("sum([1], [2])", "wrong number of arguments. got=2, want=1"),
+            (
+                "sum([9223372036854775807, 1])",
+                "integer overflow: 9223372036854775807 + 1",
+            ),
+        ];
+        assert_eval(&case);
+    }
+
+    #[test]
+    fn test_builtin_print() {
+        let case = [
+            (r#"print("a")"#, r#""a""#, "a"),
+            (r#"println("a")"#, r#""a""#, "a\n"),
+            (
+                r#"print(1); print([1, "b"])"#,
+                r#""[1, "b"]""#,
+                r#"1[1, "b"]"#,
+            ),
+            (
+                r#"let f = fn(x) { println(x) }; f(1); f(true)"#,
+                r#""true""#,
+                "1\ntrue\n",
+            ),
+            (r#"len(println("abc"))"#, "3", "abc\n"),
+            ("print()", "wrong number of arguments. got=0, want=1", ""),
+        ];
+        assert_eval_output(&case);
+    }
+
+    #[test]
+    fn test_builtin_each() {
+        let case = [
+            ("each([1, 2, 3], puts)", "null", "1\n2\n3\n"),
+            (
+                r#"each(["a", "b"], fn(x) { print(x + "!") })"#,
+                "null",
+                "a!b!",
+            ),
+            ("each([], puts)", "null", ""),
+            (
+                "let sum = 0; each([1, 2, 3], fn(x) { sum = sum + x }); sum",
+                "6",
+                "",
+            ),
+            (
+                "each([1, 2], fn(x) { puts(x); x / 0 })",
+                "division by zero",
+                "1\n",
+            ),
+            (
+                "each(1, puts)",
+                "arg to `each` not supported, got INTEGER",
+                "",
+            ),
+            (
+                "each([1], 1)",
+                "arg to `each` not supported, got INTEGER",
+                "",
+            ),
+            ("each([1])", "wrong number of arguments. got=1, want=2", ""),
+        ];
+        assert_eval_output(&case);
+    }
+
+    #[test]
+    fn test_builtin_string_case() {
+        let case = [
+            (r#"upper("abc")"#, r#""ABC""#),
+            (r#"lower("ABC")"#, r#""abc""#),
+            (r#"upper("aB1ß")"#, r#""AB1SS""#),
+            (r#"trim("  x  ")"#, r#""x""#),
+            (r#"trim("")"#, r#""""#),
+            ("upper(1)", "arg to `upper` not supported, got INTEGER"),
+            ("trim([])", "arg to `trim` not supported, got ARRAY"),
+            (
+                r#"lower("a", "b")"#,
+                "wrong number of arguments. got=2, want=1",
+            ),
+        ];
+        assert_eval(&case);
+    }
+
+    #[test]
+    fn test_builtin_chars_bytes() {
+        let case = [
+            (r#"chars("ab")"#, r#"["a", "b"]"#),
+            (r#"chars("")"#, "[]"),
+            (r#"chars("héllo")[1]"#, r#""é""#),
+            (r#"len(chars("héllo"))"#, "5"),
+            (r#"bytes("A")"#, "[65]"),
+            (r#"bytes("é")"#, "[195, 169]"),
+            (r#"len(bytes("héllo"))"#, "6"),
+            ("chars(1)", "arg to `chars` not supported, got INTEGER"),
+            ("bytes([])", "arg to `bytes` not supported, got ARRAY"),
+            ("bytes()", "wrong number of arguments. got=0, want=1"),
+        ];
+        assert_eval(&case);
+    }
+
+    #[test]
+    fn test_builtin_concat() {
+        let case = [
+            ("concat([1, 2], [3], [4, 5])", "[1, 2, 3, 4, 5]"),
+            ("concat([1, 2])", "[1, 2]"),
+            ("concat([], [1], [])", "[1]"),
+            ("concat([[1]], [[2]])", "[[1], [2]]"),
+            ("concat()", "[]"),
+            ("let a = [1]; concat(a, a); a", "[1]"),
+            (
+                "concat([1], 2)",
+                "arg to `concat` not supported, got INTEGER",
+            ),
+        ];
+        assert_eval(&case);
+    }
+
+    #[test]
+    fn test_builtin_set() {
+        let case = [
+            ("set([1, 2, 3], 1, 9)", "[1, 9, 3]"),
+            ("set([1, 2, 3], -1, 9)", "[1, 2, 9]"),
+            ("set([1, 2, 3], -3, [0])", "[[0], 2, 3]"),
+            ("let a = [1, 2]; set(a, 0, 5); a", "[1, 2]"),
+            ("set([1, 2, 3], 3, 9)", "index out of range: 3 for length 3"),
+            (
+                "set([1, 2, 3], -4, 9)",
+                "index out of range: -4 for length 3",
+            ),
+            ("set([], 0, 9)", "index out of range: 0 for length 0"),
+            (
+                r#"set("abc", 0, 9)"#,
+                "arg to `set` not supported, got STRING",
+            ),
+            (
+                r#"set([1], "0", 9)"#,
+                "arg to `set` not supported, got STRING",
+            ),
+            ("set([1], 0)", "wrong number of arguments. got=2, want=3"),
+        ];
+        assert_eval(&case);
+    }
+
+    #[test]
+    fn test_builtin_format() {
+        let case = [
+            (r#"format("{} + {} = {}", 1, 2, 3)"#, r#""1 + 2 = 3""#),
+            (r#"format("no placeholders")"#, r#""no placeholders""#),
+            (r#"format("{}!", "hi")"#, r#""hi!""#),
+            (r#"format("{}", [1, "a"])"#, r#""[1, "a"]""#),
+            (r#"format("{{}} {{{}}}", 1)"#, r#""{} {1}""#),
+            (r#"format("{ and }")"#, r#""{ and }""#),
+            (
+                r#"format("{} {}", 1)"#,
+                "`format` got 2 placeholders but 1 values",
+            ),
+            (
+                r#"format("{}", 1, 2)"#,
+                "`format` got 1 placeholders but 2 values",
+            ),
+            ("format(1)", "arg to `format` not supported, got INTEGER"),
+            (
+                "format()",
+                "wrong number of arguments. got=0, want=1 or more",
+            ),
+        ];
+        assert_eval(&case);
+    }
+
+    #[test]
+    fn test_builtin_index_of() {
+        let case = [
+            ("index_of([10, 20, 30], 20)", "1"),
+            ("index_of([10, 20, 30], 40)", "-1"),
+            ("index_of([1, [2], 2], [2])", "1"),
+            (r#"index_of([1, "1"], "1")"#, "1"),
+            (r#"index_of("hello", "ll")"#, "2"),
+            (r#"index_of("hello", "z")"#, "-1"),
+            (r#"index_of("hello", "")"#, "0"),
+            (r#"index_of("äbc", "c")"#, "2"),
+            (
+                r#"index_of("abc", 1)"#,
+                "arg to `index_of` not supported, got INTEGER",
+            ),
+            (
+                "index_of(1, 1)",
+                "arg to `index_of` not supported, got INTEGER",
+            ),
+            ("index_of([1])", "wrong number of arguments. got=1, want=2"),
+        ];
+        assert_eval(&case);
+    }
+
+    #[test]
+    fn test_builtin_math() {
+        let case = [
+            ("abs(-5)", "5"),
+            ("abs(5)", "5"),
+            ("abs(-1.5)", "1.5"),
+            ("pow(2, 10)", "1024"),
+            ("pow(-3, 3)", "-27"),
+            ("pow(5, 0)", "1"),
+            ("pow(2.0, 3)", "8.0"),
+            ("pow(4, 0.5)", "2.0"),
+            ("sqrt(16)", "4.0"),
+            ("sqrt(2.25)", "1.5"),
+            ("pow(2, 100)", "integer overflow: pow(2, 100)"),
+            (
+                "pow(2, 10000000000)",
+                "integer overflow: pow(2, 10000000000)",
+            ),
+            (
+                "abs(-9223372036854775807 - 1)",
+                "integer overflow: abs(-9223372036854775808)",
+            ),
+            (
+                "pow(2, -1)",
+                "exponent of `pow` must be non-negative, got -1",
+            ),
+            ("sqrt(-4)", "cannot take `sqrt` of a negative number: -4"),
+            (r#"abs("a")"#, "arg to `abs` not supported, got STRING"),
+            ("pow(2, true)", "arg to `pow` not supported, got BOOLEAN"),
+            ("pow([], 1)", "arg to `pow` not supported, got ARRAY"),
+            (
+                "sqrt(null_value)",
+                "Uncaught ReferenceError: null_value is not defined",
+            ),
+            ("pow(2)", "wrong number of arguments. got=1, want=2"),
+        ];
+        assert_eval(&case);
+    }
+
+    #[test]
+    fn test_builtin_assert() {
+        let case = [
+            ("assert(true)", "null"),
+            ("assert(1 + 1 == 2)", "null"),
+            ("assert([])", "null"),
+            (r#"assert(true, "never shown")"#, "null"),
+            ("assert(false)", "assertion failed"),
+            ("assert(null)", "assertion failed"),
+            (
+                r#"assert(1 > 2, "1 is not greater than 2")"#,
+                "assertion failed: 1 is not greater than 2",
+            ),
+            ("assert(false, [1])", "assertion failed: [1]"),
+            ("assert(false); 1", "assertion failed"),
+            ("assert()", "wrong number of arguments. got=0, want=1 or 2"),
+        ];
+        assert_eval(&case);
+    }
+
+    #[test]
+    fn test_builtin_repeat() {
+        let case = [
+            ("repeat(0, 3)", "[0, 0, 0]"),
+            (r#"repeat("x", 2)"#, r#"["x", "x"]"#),
+            ("repeat(1, 0)", "[]"),
+            ("repeat([1, 2], 2)", "[[1, 2], [1, 2]]"),
+            ("len(repeat(null, 10000))", "10000"),
+            (
+                "repeat(1, -1)",
+                "count to `repeat` must not be negative, got -1",
+            ),
+            (
+                "repeat(1, 100000000)",
+                "repeat too large, at most 10000000 elements are allowed",
+            ),
+            (
+                "repeat(range(100000), 10000000)",
+                "repeat too large, at most 10000000 elements are allowed",
+            ),
+            (
+                "repeat([1, [2, 3]], 5000000)",
+                "repeat too large, at most 10000000 elements are allowed",
+            ),
+            (r#"len(repeat({"a": [1, 2]}, 100000))"#, "100000"),
+            (
+                r#"repeat(1, "2")"#,
+                "arg to `repeat` not supported, got STRING",
+            ),
+            ("repeat(1)", "wrong number of arguments. got=1, want=2"),
+        ];
+        assert_eval(&case);
+    }
+
+    #[test]
+    fn test_map() {
+        let case = [(
+            r#"
+            let my_map = fn(array, f) {
+                let iter = fn(arr, accumulated) {
+                    if(len(arr) == 0){
+                        accumulated
+                    } else {
+                        iter(rest(arr), push(accumulated, f(first(arr))))
+                    }
+                };
+
+                iter(array, []);
+            };
+
+            let double = fn(x){ return x * 2 };
+            my_map([1, 2, 3, 4], double);
+            "#,
+            "[2, 4, 6, 8]",
+        )];
+        assert_eval(&case);
+    }
 }
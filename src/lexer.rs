@@ -1,10 +1,16 @@
-use crate::token::Token;
+use crate::token::{Span, Token};
 
 #[derive(Debug)]
 pub struct Lexer<'a> {
     input: std::str::Chars<'a>,
     cur: char,
     peek: char,
+    line: usize,
+    col: usize,
+    // position of the last token returned by `next_token`
+    start: Span,
+    // set once the iterator has reached `Token::Eof`
+    done: bool,
 }
 
 impl<'a> Lexer<'a> {
@@ -13,14 +19,26 @@ impl<'a> Lexer<'a> {
             input: input.chars(),
             cur: '\u{0}',
             peek: '\u{0}',
+            line: 1,
+            col: 1,
+            start: Span::default(),
+            done: false,
         };
         l.read_char();
         l.read_char();
+        l.line = 1;
+        l.col = 1;
         l
     }
 
     fn read_char(&mut self) -> char {
         let c = self.cur;
+        if c == '\n' {
+            self.line += 1;
+            self.col = 1;
+        } else {
+            self.col += 1;
+        }
         self.cur = self.peek;
         self.peek = self.input.next().unwrap_or('\u{0}');
         c
@@ -30,8 +48,33 @@ impl<'a> Lexer<'a> {
         self.peek == c
     }
 
+    fn span(&self) -> Span {
+        Span {
+            line: self.line,
+            col: self.col,
+        }
+    }
+
+    /// Returns the next token along with the position it starts at.
+    pub fn next_token_with_span(&mut self) -> (Token, Span) {
+        let token = self.next_token();
+        (token, self.start)
+    }
+
     pub fn next_token(&mut self) -> Token {
-        self.skip_whitespace();
+        loop {
+            self.skip_whitespace();
+            self.start = self.span();
+            if self.cur == '/' && self.peek_char('/') {
+                self.skip_line_comment();
+            } else if self.cur == '/' && self.peek_char('*') {
+                if !self.skip_block_comment() {
+                    return Token::Illegal("/*".to_string());
+                }
+            } else {
+                break;
+            }
+        }
         let token = match self.cur {
             '=' => {
                 if self.peek_char('=') {
@@ -43,6 +86,33 @@ impl<'a> Lexer<'a> {
                 }
             }
             ':' => Token::Colon,
+            '?' => {
+                if self.peek_char('?') {
+                    // consume peek_char
+                    self.read_char();
+                    Token::DoubleQuestion
+                } else {
+                    Token::Question
+                }
+            }
+            '&' => {
+                if self.peek_char('&') {
+                    // consume peek_char
+                    self.read_char();
+                    Token::And
+                } else {
+                    Token::Illegal('&'.to_string())
+                }
+            }
+            '|' => {
+                if self.peek_char('|') {
+                    // consume peek_char
+                    self.read_char();
+                    Token::Or
+                } else {
+                    Token::Illegal('|'.to_string())
+                }
+            }
             ';' => Token::Semicolon,
             ',' => Token::Comma,
             '(' => Token::LParen,
@@ -64,17 +134,34 @@ impl<'a> Lexer<'a> {
                     Token::Bang
                 }
             }
-            '<' => Token::Gt,
-            '>' => Token::Lt,
+            '<' => {
+                if self.peek_char('=') {
+                    // consume peek_char
+                    self.read_char();
+                    Token::Le
+                } else {
+                    Token::Lt
+                }
+            }
+            '>' => {
+                if self.peek_char('=') {
+                    // consume peek_char
+                    self.read_char();
+                    Token::Ge
+                } else {
+                    Token::Gt
+                }
+            }
             '"' => self.read_string(),
             '\u{0}' => Token::Eof,
             c => {
                 if is_letter(c) {
                     return self.read_identifier();
                 } else if is_digit(c) {
-                    return self.read_integer();
+                    return self.read_number();
                 } else {
-                    return Token::Illegal(c.to_string());
+                    // consumed like any other character so lexing can go on after it
+                    Token::Illegal(c.to_string())
                 }
             }
         };
@@ -84,7 +171,7 @@ impl<'a> Lexer<'a> {
 
     fn read_identifier(&mut self) -> Token {
         let mut ident = String::new();
-        while is_letter(self.cur) {
+        while is_letter(self.cur) || is_digit(self.cur) {
             ident.push(self.read_char());
         }
         if let Some(tok) = Token::keyword(&ident) {
@@ -93,14 +180,36 @@ impl<'a> Lexer<'a> {
         Token::Ident(ident)
     }
 
-    fn read_integer(&mut self) -> Token {
-        let mut integer = String::new();
-        while is_digit(self.cur) {
-            integer.push(self.read_char());
+    fn read_number(&mut self) -> Token {
+        let mut number = String::new();
+        while is_digit(self.cur) || self.cur == '_' {
+            number.push(self.read_char());
+        }
+        let is_float = self.cur == '.';
+        if is_float {
+            // a trailing dot like `5.` is read as `5.0`,
+            // more than one dot like `1.2.3` is illegal
+            while is_digit(self.cur) || self.cur == '_' || self.cur == '.' {
+                number.push(self.read_char());
+            }
         }
-        match integer.parse::<i64>() {
-            Ok(int) => Token::Int(int),
-            Err(_) => Token::Illegal(integer),
+        // underscores may only separate digits, as in `1_000_000`
+        if number
+            .split('.')
+            .any(|part| part.starts_with('_') || part.ends_with('_') || part.contains("__"))
+        {
+            return Token::Illegal(number);
+        }
+        let digits = number.replace('_', "");
+        if !is_float {
+            return match digits.parse::<i64>() {
+                Ok(int) => Token::Int(int),
+                Err(_) => Token::Illegal(number),
+            };
+        }
+        match digits.parse::<f64>() {
+            Ok(float) => Token::Float(float),
+            Err(_) => Token::Illegal(number),
         }
     }
 
@@ -109,6 +218,10 @@ impl<'a> Lexer<'a> {
         // consume "
         self.read_char();
         while self.cur != '"' {
+            if self.cur == '\u{0}' {
+                // unterminated, reported with its opening quote
+                return Token::Illegal(format!("\"{}", string));
+            }
             string.push(self.read_char());
         }
         Token::String(string)
@@ -119,14 +232,56 @@ impl<'a> Lexer<'a> {
             self.read_char();
         }
     }
+
+    fn skip_line_comment(&mut self) {
+        while self.cur != '\n' && self.cur != '\u{0}' {
+            self.read_char();
+        }
+    }
+
+    /// Skips a `/* ... */` comment. Block comments do not nest.
+    /// Returns false if the input ends before the closing `*/`.
+    fn skip_block_comment(&mut self) -> bool {
+        // consume /*
+        self.read_char();
+        self.read_char();
+        while self.cur != '\u{0}' {
+            if self.cur == '*' && self.peek_char('/') {
+                // consume */
+                self.read_char();
+                self.read_char();
+                return true;
+            }
+            self.read_char();
+        }
+        false
+    }
+}
+
+/// Yields every token up to, but not including, `Token::Eof`.
+impl Iterator for Lexer<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        if self.done {
+            return None;
+        }
+        match self.next_token() {
+            Token::Eof => {
+                self.done = true;
+                None
+            }
+            token => Some(token),
+        }
+    }
 }
 
 fn is_letter(c: char) -> bool {
-    ('a'..='z').contains(&c) || ('A'..='Z').contains(&c)
+    c.is_ascii_alphabetic() || c == '_'
 }
 
 fn is_digit(c: char) -> bool {
-    ('0'..='9').contains(&c)
+    c.is_ascii_digit()
 }
 
 #[cfg(test)]
@@ -143,7 +298,7 @@ mod test {
 
     #[test]
     fn test_next_token() {
-        let input = "=+(){},!-/*5:;";
+        let input = "=+(){},!-/ *5:;";
         let expected = vec![
             Token::Assign,
             Token::Plus,
@@ -164,6 +319,87 @@ mod test {
         assert_tokens(input, expected);
     }
 
+    #[test]
+    fn test_iterator() {
+        let mut l = Lexer::new("let add = fn(x) { x + 1 };");
+        let tokens: Vec<Token> = l.by_ref().collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Let,
+                Token::Ident("add".to_string()),
+                Token::Assign,
+                Token::Function,
+                Token::LParen,
+                Token::Ident("x".to_string()),
+                Token::RParen,
+                Token::LBrace,
+                Token::Ident("x".to_string()),
+                Token::Plus,
+                Token::Int(1),
+                Token::RBrace,
+                Token::Semicolon,
+            ]
+        );
+        assert_eq!(l.next(), None);
+        assert_eq!(Lexer::new("").count(), 0);
+    }
+
+    #[test]
+    fn test_logical_operators() {
+        let input = "a && b || c; true and false; not true or x; andy android order nothing";
+        let expected = vec![
+            Token::Ident("a".to_string()),
+            Token::And,
+            Token::Ident("b".to_string()),
+            Token::Or,
+            Token::Ident("c".to_string()),
+            Token::Semicolon,
+            Token::True,
+            Token::And,
+            Token::False,
+            Token::Semicolon,
+            Token::Bang,
+            Token::True,
+            Token::Or,
+            Token::Ident("x".to_string()),
+            Token::Semicolon,
+            Token::Ident("andy".to_string()),
+            Token::Ident("android".to_string()),
+            Token::Ident("order".to_string()),
+            Token::Ident("nothing".to_string()),
+            Token::Eof,
+        ];
+        assert_tokens(input, expected);
+    }
+
+    #[test]
+    fn test_unterminated_string() {
+        let input = r#"let a = "abc"#;
+        let expected = vec![
+            Token::Let,
+            Token::Ident("a".to_string()),
+            Token::Assign,
+            Token::Illegal(r#""abc"#.to_string()),
+            Token::Eof,
+        ];
+        assert_tokens(input, expected);
+    }
+
+    #[test]
+    fn test_illegal_character() {
+        let input = "1 & 2 | @";
+        let expected = vec![
+            Token::Int(1),
+            Token::Illegal("&".to_string()),
+            Token::Int(2),
+            Token::Illegal("|".to_string()),
+            Token::Illegal("@".to_string()),
+            Token::Eof,
+        ];
+        assert_tokens(input, expected);
+    }
+
     #[test]
     fn test_let_stmt() {
         let input = r#"let five = 5;
@@ -185,6 +421,21 @@ mod test {
         assert_tokens(input, expected);
     }
 
+    #[test]
+    fn test_ident() {
+        let input = "foo_bar x1 _tmp counter2 5x";
+        let expected = vec![
+            Token::Ident("foo_bar".to_string()),
+            Token::Ident("x1".to_string()),
+            Token::Ident("_tmp".to_string()),
+            Token::Ident("counter2".to_string()),
+            Token::Int(5),
+            Token::Ident("x".to_string()),
+            Token::Eof,
+        ];
+        assert_tokens(input, expected);
+    }
+
     #[test]
     fn test_func() {
         let input = "let add = fn(x, y){x + y};";
@@ -210,14 +461,30 @@ mod test {
     }
 
     #[test]
-    fn test_gt_le() {
+    fn test_lt_gt() {
         let input = "5 < 10 > 5";
         let expected = vec![
             Token::Int(5),
+            Token::Lt,
+            Token::Int(10),
             Token::Gt,
+            Token::Int(5),
+            Token::Eof,
+        ];
+        assert_tokens(input, expected);
+    }
+
+    #[test]
+    fn test_le_ge() {
+        let input = "5 <= 10 >= 5 < =";
+        let expected = vec![
+            Token::Int(5),
+            Token::Le,
             Token::Int(10),
-            Token::Lt,
+            Token::Ge,
             Token::Int(5),
+            Token::Lt,
+            Token::Assign,
             Token::Eof,
         ];
         assert_tokens(input, expected);
@@ -229,7 +496,7 @@ mod test {
             Token::If,
             Token::LParen,
             Token::Int(5),
-            Token::Gt,
+            Token::Lt,
             Token::Int(10),
             Token::RParen,
             Token::LBrace,
@@ -247,6 +514,37 @@ mod test {
         assert_tokens(input, expected);
     }
 
+    #[test]
+    fn test_question() {
+        let input = "a ?? b ? c : d";
+        let expected = vec![
+            Token::Ident("a".to_string()),
+            Token::DoubleQuestion,
+            Token::Ident("b".to_string()),
+            Token::Question,
+            Token::Ident("c".to_string()),
+            Token::Colon,
+            Token::Ident("d".to_string()),
+            Token::Eof,
+        ];
+        assert_tokens(input, expected);
+    }
+
+    #[test]
+    fn test_null() {
+        let input = "let x = null; nullable";
+        let expected = vec![
+            Token::Let,
+            Token::Ident("x".to_string()),
+            Token::Assign,
+            Token::Null,
+            Token::Semicolon,
+            Token::Ident("nullable".to_string()),
+            Token::Eof,
+        ];
+        assert_tokens(input, expected);
+    }
+
     #[test]
     fn test_eq() {
         let input = "10 == 10; 10 != 9";
@@ -263,6 +561,93 @@ mod test {
         assert_tokens(input, expected);
     }
 
+    #[test]
+    fn test_float() {
+        let input = "1.25 * 2; 5. 0.5 1.2.3";
+        let expected = vec![
+            Token::Float(1.25),
+            Token::Asterisk,
+            Token::Int(2),
+            Token::Semicolon,
+            Token::Float(5.0),
+            Token::Float(0.5),
+            Token::Illegal("1.2.3".to_string()),
+            Token::Eof,
+        ];
+        assert_tokens(input, expected);
+    }
+
+    #[test]
+    fn test_digit_separator() {
+        let input = "1_000 1_000_000 1_0.2_5 1_ 1__0 1_.5 1._5 _1";
+        let expected = vec![
+            Token::Int(1000),
+            Token::Int(1_000_000),
+            Token::Float(10.25),
+            Token::Illegal("1_".to_string()),
+            Token::Illegal("1__0".to_string()),
+            Token::Illegal("1_.5".to_string()),
+            Token::Illegal("1._5".to_string()),
+            // a leading underscore starts an identifier
+            Token::Ident("_1".to_string()),
+            Token::Eof,
+        ];
+        assert_tokens(input, expected);
+    }
+
+    #[test]
+    fn test_comment() {
+        let input = "// comment at start
+        let a = 1; // comment at end
+        1 + /* x */ 2;
+        /* outer /* not nested */ 3 /
+        // last line";
+        let expected = vec![
+            Token::Let,
+            Token::Ident("a".to_string()),
+            Token::Assign,
+            Token::Int(1),
+            Token::Semicolon,
+            Token::Int(1),
+            Token::Plus,
+            Token::Int(2),
+            Token::Semicolon,
+            Token::Int(3),
+            Token::Slash,
+            Token::Eof,
+        ];
+        assert_tokens(input, expected);
+    }
+
+    #[test]
+    fn test_unterminated_block_comment() {
+        let input = "1 /* never closed";
+        let expected = vec![Token::Int(1), Token::Illegal("/*".to_string()), Token::Eof];
+        assert_tokens(input, expected);
+    }
+
+    #[test]
+    fn test_span() {
+        let input = "let a = 1;
+  a +
+ /* x */ b";
+        let expected = vec![
+            (Token::Let, 1, 1),
+            (Token::Ident("a".to_string()), 1, 5),
+            (Token::Assign, 1, 7),
+            (Token::Int(1), 1, 9),
+            (Token::Semicolon, 1, 10),
+            (Token::Ident("a".to_string()), 2, 3),
+            (Token::Plus, 2, 5),
+            (Token::Ident("b".to_string()), 3, 10),
+            (Token::Eof, 3, 11),
+        ];
+        let mut l = Lexer::new(input);
+        for (token, line, col) in expected {
+            assert_eq!(l.next_token_with_span(), (token, Span { line, col }));
+        }
+    }
+
     #[test]
     fn test_string() {
         let input = r#""foobar""#;
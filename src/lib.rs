@@ -9,29 +9,80 @@ pub mod operator;
 pub mod parser;
 pub mod token;
 
-use std::{ffi::OsStr, fs, path::Path};
+use std::{ffi::OsStr, panic, path::Path, thread};
 
+use error::{MonkeyError, Result};
 use evaluator::Evaluator;
 use lexer::Lexer;
+use object::Object;
 use parser::Parser;
 
-pub fn execute(file_path: &str) -> String {
-    let ext = get_file_extension(file_path).unwrap();
-    if ext == "monkey" {
-        let code = fs::read_to_string(file_path).unwrap();
-        let mut e = Evaluator::new();
-        let l = Lexer::new(code.as_str());
-        let mut p = Parser::new(l);
-        let program = p.parse_program().unwrap();
-        match e.eval(program) {
-            Ok(o) => o.to_string(),
-            Err(err) => err.to_string(),
-        }
-    } else {
-        todo!()
+/// Stack size for threads running an `Evaluator` with the default `max_depth`.
+pub const STACK_SIZE: usize = 64 * 1024 * 1024;
+
+/// Runs `f` on a thread with `STACK_SIZE` bytes of stack and returns its result.
+fn with_stack<T: Send>(f: impl FnOnce() -> T + Send) -> T {
+    thread::scope(|scope| {
+        let worker = thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn_scoped(scope, f)
+            .expect("failed to spawn the evaluation thread");
+        worker
+            .join()
+            .unwrap_or_else(|err| panic::resume_unwind(err))
+    })
+}
+
+/// The result of evaluating on another thread, handed back to the calling one.
+struct Detached(Result<Object>);
+
+// SAFETY: the `Rc`s in the result are only shared with the evaluator that produced it,
+// which is dropped on the evaluation thread before the result is handed back, and
+// joining the thread orders those drops before any use on the calling thread.
+unsafe impl Send for Detached {}
+
+/// Lexes, parses and evaluates `source` in a fresh environment,
+/// on a thread with enough stack for the default `max_depth`.
+pub fn eval_str(source: &str) -> Result<Object> {
+    with_stack(|| Detached(Evaluator::new().run(source))).0
+}
+
+/// Lexes and parses `source` without evaluating it and returns every syntax error found,
+/// each with its position. Valid input gives an empty vec.
+pub fn check(source: &str) -> Vec<MonkeyError> {
+    let mut p = Parser::new(Lexer::new(source));
+    let (_, errors) = p.parse_program_collect();
+    errors
+}
+
+/// Runs a `.monkey` file in a fresh environment and returns its printed result.
+/// Like `eval_str`, it runs on a thread with enough stack for the default `max_depth`.
+/// The file may `import` other files, unlike code run with `eval_str`.
+/// A missing or unreadable file, a wrong extension and parse or evaluation errors
+/// are all returned as errors.
+pub fn try_execute(file_path: &str) -> Result<String> {
+    match get_file_extension(file_path) {
+        Some("monkey") => with_stack(|| {
+            let mut e = Evaluator::new();
+            e.allow_import = true;
+            Ok(e.run_file(Path::new(file_path))?.to_string())
+        }),
+        Some(ext) => Err(MonkeyError::Custom(format!(
+            "unsupported file extension: .{}",
+            ext
+        ))),
+        None => Err(MonkeyError::Custom(format!(
+            "missing file extension: {}",
+            file_path
+        ))),
     }
 }
 
+/// Like `try_execute`, but with any error rendered as the result.
+pub fn execute(file_path: &str) -> String {
+    try_execute(file_path).unwrap_or_else(|err| err.to_string())
+}
+
 fn get_file_extension(filename: &str) -> Option<&str> {
     Path::new(filename).extension().and_then(OsStr::to_str)
 }
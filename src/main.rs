@@ -1,6 +1,16 @@
-use std::io::Result;
+use std::{env, fs, io::Result, path::Path, process, thread};
 
-use rmonkey::{evaluator::Evaluator, lexer::Lexer, parser::Parser};
+use rmonkey::{
+    error::{self, MonkeyError},
+    evaluator::Evaluator,
+    lexer::Lexer,
+    parser::Parser,
+    token::Token,
+};
+
+const PROMPT: &str = "> ";
+/// Shown while a statement spans several lines.
+const CONTINUATION_PROMPT: &str = "... ";
 
 fn prompt(s: &str) -> Result<()> {
     use std::io::{stdout, Write};
@@ -10,26 +20,306 @@ fn prompt(s: &str) -> Result<()> {
     stdout.flush()
 }
 
+#[derive(Debug, PartialEq)]
+enum Action {
+    Continue,
+    Quit,
+}
+
+/// Handles a REPL command such as `:load <file>`, `:reset`, `:env` or `:quit`.
+fn process_command(e: &mut Evaluator, line: &str) -> error::Result<Action> {
+    let mut parts = line.trim().splitn(2, char::is_whitespace);
+    match (parts.next(), parts.next().map(str::trim)) {
+        (Some(":load"), Some(path)) if !path.is_empty() => {
+            e.run_file(Path::new(path))?;
+            Ok(Action::Continue)
+        }
+        (Some(":reset"), None) => {
+            *e = evaluator();
+            Ok(Action::Continue)
+        }
+        (Some(":env"), None) => {
+            for (name, val) in e.bindings() {
+                println!("{} = {}", name, val);
+            }
+            Ok(Action::Continue)
+        }
+        (Some(":quit"), None) => Ok(Action::Quit),
+        // there is nothing pending to cancel
+        (Some(":cancel"), None) => Ok(Action::Continue),
+        _ => Err(MonkeyError::Custom(format!("unknown command: {}", line))),
+    }
+}
+
+/// Evaluates one line of input and returns what should be printed.
+/// Parse and evaluation errors are returned instead of aborting the REPL.
+fn process_line(e: &mut Evaluator, line: &str) -> error::Result<String> {
+    e.run(line).map(|o| o.to_string())
+}
+
+/// Whether `input` can be run as is, rather than being the start of a statement that
+/// continues on the next line, like a function whose closing brace hasn't been typed yet.
+/// Input with any other syntax error counts as complete so the error is shown right away.
+fn is_complete(input: &str) -> bool {
+    let mut depth = 0;
+    for token in Lexer::new(input) {
+        match token {
+            Token::LParen | Token::LBrace | Token::LBracket => depth += 1,
+            Token::RParen | Token::RBrace | Token::RBracket => depth -= 1,
+            // an unterminated string or block comment
+            Token::Illegal(val) if val.starts_with('"') || val == "/*" => return false,
+            _ => {}
+        }
+    }
+    if depth > 0 {
+        return false;
+    }
+    let (_, errors) = Parser::new(Lexer::new(input)).parse_program_collect();
+    !errors.iter().any(|err| {
+        matches!(
+            err,
+            MonkeyError::UnexpectedToken(_, Token::Eof, _)
+                | MonkeyError::NoPrefixParse(Token::Eof, _)
+        )
+    })
+}
+
+/// Runs a `.monkey` file and returns its result.
+/// A file that can't be read is reported as an error instead of panicking.
+fn run_file(path: &str) -> error::Result<String> {
+    rmonkey::try_execute(path)
+}
+
+/// Parses a `.monkey` file and returns its AST in the tree form of `Program::pretty`.
+fn dump_ast(path: &str) -> error::Result<String> {
+    let code = fs::read_to_string(path)
+        .map_err(|err| MonkeyError::Custom(format!("cannot read {}: {}", path, err)))?;
+    let mut p = Parser::new(Lexer::new(&code));
+    Ok(p.parse_program()?.pretty(2))
+}
+
+/// Evaluates the code passed with `-e`/`--eval` in a fresh environment.
+fn run_eval(code: &str) -> error::Result<String> {
+    evaluator().run(code).map(|o| o.to_string())
+}
+
+/// An evaluator for code typed or passed in by the user, which may import files.
+fn evaluator() -> Evaluator {
+    let mut e = Evaluator::new();
+    e.allow_import = true;
+    e
+}
+
 fn main() {
+    // deep recursion in monkey code needs more stack than the main thread has
+    let child = thread::Builder::new()
+        .stack_size(rmonkey::STACK_SIZE)
+        .spawn(run)
+        .unwrap();
+    child.join().unwrap();
+}
+
+fn run() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.as_slice() {
+        [] => repl(),
+        [flag, code] if flag == "-e" || flag == "--eval" => match run_eval(code) {
+            Ok(out) => println!("{}", out),
+            Err(err) => {
+                eprintln!("{}", err);
+                process::exit(1);
+            }
+        },
+        [flag, path] if flag == "--ast" => match dump_ast(path) {
+            Ok(out) => print!("{}", out),
+            Err(err) => {
+                eprintln!("{}", err);
+                process::exit(1);
+            }
+        },
+        [path] => match run_file(path) {
+            Ok(out) => println!("{}", out),
+            Err(err) => {
+                eprintln!("{}", err);
+                process::exit(1);
+            }
+        },
+        _ => {
+            eprintln!("usage: rmonkey [file | -e <code> | --ast <file>]");
+            process::exit(2);
+        }
+    }
+}
+
+fn repl() {
     use std::io::{stdin, BufRead, BufReader};
     let stdin = stdin();
     let stdin = stdin.lock();
     let stdin = BufReader::new(stdin);
     let mut lines = stdin.lines();
-    let mut e = Evaluator::new();
+    let mut e = evaluator();
+    let primary = env::var("RMONKEY_PROMPT").unwrap_or_else(|_| PROMPT.to_string());
+    // lines of a statement that isn't complete yet
+    let mut input = String::new();
 
     loop {
-        prompt("> ").unwrap();
-        if let Some(Ok(line)) = lines.next() {
-            let l = Lexer::new(line.as_str());
-            let mut p = Parser::new(l);
-            let program = p.parse_program().unwrap();
-            match e.eval(program) {
-                Ok(o) => {
-                    println!("{}", o);
-                }
+        prompt(if input.is_empty() {
+            &primary
+        } else {
+            CONTINUATION_PROMPT
+        })
+        .unwrap();
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            // EOF (Ctrl-D) or unreadable input
+            _ => break,
+        };
+        if input.is_empty() && line.starts_with(':') {
+            match process_command(&mut e, &line) {
+                Ok(Action::Quit) => break,
+                Ok(Action::Continue) => {}
                 Err(err) => eprintln!("{}", err),
             }
+            continue;
         }
+        if let Some(code) = push_line(&mut input, &line) {
+            match process_line(&mut e, &code) {
+                Ok(out) => println!("{}", out),
+                Err(err) => eprintln!("{}", err),
+            }
+        }
+    }
+}
+
+/// Adds `line` to the `pending` lines of a statement and returns the statement once
+/// it is complete. An empty line or `:cancel` throws away the pending lines instead.
+fn push_line(pending: &mut String, line: &str) -> Option<String> {
+    if !pending.is_empty() && matches!(line.trim(), "" | ":cancel") {
+        pending.clear();
+        return None;
+    }
+    pending.push_str(line);
+    pending.push('\n');
+    if !is_complete(pending) {
+        return None;
+    }
+    Some(std::mem::take(pending))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_line() {
+        let mut e = Evaluator::new();
+        assert_eq!(process_line(&mut e, "let a = 2;").unwrap(), "null");
+        assert!(process_line(&mut e, "let = ;").is_err());
+        assert!(process_line(&mut e, "a +").is_err());
+        assert_eq!(process_line(&mut e, "a * 3").unwrap(), "6");
+    }
+
+    #[test]
+    fn test_is_complete() {
+        let complete = [
+            "",
+            "let a = 1;",
+            "let f = fn(x) {\n  x * 2\n};",
+            "if (a) { 1 } else { 2 }",
+            r#"let s = "{";"#,
+            // other syntax errors are reported right away
+            "let = 1;",
+            "1 }",
+        ];
+        for input in complete.iter() {
+            assert!(is_complete(input), "{:?}", input);
+        }
+        let incomplete = [
+            "let f = fn(x) {",
+            "let f = fn(x) {\n  if (x) {\n    1\n  }",
+            "add(1,",
+            "[1, 2",
+            "if (a) { 1 } else",
+            "let a =",
+            "1 +",
+            r#"let s = "abc"#,
+            "1; /* comment",
+        ];
+        for input in incomplete.iter() {
+            assert!(!is_complete(input), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_load() {
+        let mut e = Evaluator::new();
+        let action = process_command(&mut e, ":load tests/codes/func.monkey").unwrap();
+        assert_eq!(action, Action::Continue);
+        assert_eq!(e.run("add(2, 3)").unwrap().to_string(), "5");
+        assert!(process_command(&mut e, ":load tests/codes/missing.monkey").is_err());
+    }
+
+    #[test]
+    fn test_run_file() {
+        assert_eq!(run_file("tests/codes/func.monkey").unwrap(), "3");
+        assert!(run_file("tests/codes/missing.monkey")
+            .unwrap_err()
+            .to_string()
+            .starts_with("cannot read tests/codes/missing.monkey"));
+    }
+
+    #[test]
+    fn test_run_eval() {
+        assert_eq!(run_eval("let a = 2; a * 3").unwrap(), "6");
+        assert!(run_eval("let = ;").is_err());
+        assert_eq!(
+            run_eval("1 + true").unwrap_err().to_string(),
+            "type mismatch: INTEGER + BOOLEAN"
+        );
+    }
+
+    #[test]
+    fn test_dump_ast() {
+        let out = dump_ast("tests/codes/func.monkey").unwrap();
+        assert!(out.starts_with("Program\n  LetStatement\n    Ident add\n    FuncLiteral\n"));
+        assert!(dump_ast("tests/codes/missing.monkey").is_err());
+    }
+
+    #[test]
+    fn test_push_line() {
+        let mut pending = String::new();
+        assert_eq!(push_line(&mut pending, "let f = fn(x) {"), None);
+        assert_eq!(push_line(&mut pending, "  x * 2"), None);
+        assert_eq!(
+            push_line(&mut pending, "};").as_deref(),
+            Some("let f = fn(x) {\n  x * 2\n};\n")
+        );
+        assert!(pending.is_empty());
+        // a stray bracket can be abandoned with an empty line or `:cancel`
+        assert_eq!(push_line(&mut pending, "add(1,"), None);
+        assert_eq!(push_line(&mut pending, ""), None);
+        assert!(pending.is_empty());
+        assert_eq!(push_line(&mut pending, "{"), None);
+        assert_eq!(push_line(&mut pending, ":cancel"), None);
+        assert!(pending.is_empty());
+        assert_eq!(push_line(&mut pending, "1 + 2").as_deref(), Some("1 + 2\n"));
+    }
+
+    #[test]
+    fn test_reset_and_quit() {
+        let mut e = Evaluator::new();
+        e.run("let a = 1;").unwrap();
+        assert_eq!(e.bindings().len(), 1);
+        process_command(&mut e, ":reset").unwrap();
+        assert!(e.bindings().is_empty());
+        assert_eq!(process_command(&mut e, ":quit").unwrap(), Action::Quit);
+        assert_eq!(
+            process_command(&mut e, ":cancel").unwrap(),
+            Action::Continue
+        );
+        assert_eq!(
+            process_command(&mut e, ":foo").unwrap_err().to_string(),
+            "unknown command: :foo"
+        );
     }
 }
@@ -1,37 +1,58 @@
-use crate::error::Result;
-use std::fmt;
+use crate::error::{MonkeyError, Result};
+use std::{cell::RefCell, fmt, rc::Rc};
 
 use crate::{
-    ast::{Expr, Stmt},
+    ast::{fmt_float, Expr, Stmt},
     environment::Environment,
+    evaluator::Evaluator,
 };
 
+/// Signature of builtins, both the static ones and those registered by a host.
+pub type BuiltinFn = fn(&mut Evaluator, Vec<Object>) -> Result<Object>;
+
+/// Numbers, booleans and `null` are stored inline, so creating one never allocates
+/// and, unlike the reference implementation, there are no shared `TRUE`/`FALSE`/`NULL` objects.
+///
+/// With the `serde` feature, builtins and the captured environment of functions
+/// are skipped since they can't be serialized.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Object {
     Integer(i64),
+    Float(f64),
     Boolean(bool),
     String(String),
     Null,
     ReturnValue(Box<Object>),
+    Break,
+    Continue,
     FunctionLiteral {
         params: Vec<Expr>,
         body: Stmt,
-        env: Environment,
+        #[cfg_attr(feature = "serde", serde(skip))]
+        env: Rc<RefCell<Environment>>,
     },
-    BuiltIn(fn(Vec<Object>) -> Result<Object>),
+    #[cfg_attr(feature = "serde", serde(skip))]
+    BuiltIn(BuiltinFn),
     Array {
         elements: Vec<Object>,
     },
+    Hash {
+        pairs: Vec<(Object, Object)>,
+    },
 }
 
 impl fmt::Display for Object {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Object::Integer(val) => write!(f, "{}", val),
+            Object::Float(val) => write!(f, "{}", fmt_float(*val)),
             Object::String(val) => write!(f, r#""{}""#, val),
             Object::Boolean(bool) => write!(f, "{}", bool),
             Object::Null => write!(f, "null"),
             Object::ReturnValue(obj) => write!(f, "{}", obj),
+            Object::Break => write!(f, "break"),
+            Object::Continue => write!(f, "continue"),
             Object::FunctionLiteral { body, params, .. } => {
                 write!(
                     f,
@@ -44,7 +65,7 @@ impl fmt::Display for Object {
                     body
                 )
             }
-            Object::BuiltIn(_) => todo!(),
+            Object::BuiltIn(_) => write!(f, "builtin function"),
             Object::Array { elements } => {
                 write!(
                     f,
@@ -56,24 +77,75 @@ impl fmt::Display for Object {
                         .join(", ")
                 )
             }
+            Object::Hash { pairs } => {
+                write!(
+                    f,
+                    "{{{}}}",
+                    pairs
+                        .iter()
+                        .map(|(k, v)| format!("{}: {}", k, v))
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                )
+            }
+        }
+    }
+}
+
+/// Structural equality. Functions and builtins are never equal to anything,
+/// and values of different types are never equal.
+impl PartialEq for Object {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Object::Integer(l), Object::Integer(r)) => l == r,
+            (Object::Float(l), Object::Float(r)) => l == r,
+            (Object::Boolean(l), Object::Boolean(r)) => l == r,
+            (Object::String(l), Object::String(r)) => l == r,
+            (Object::Null, Object::Null) => true,
+            (Object::ReturnValue(l), Object::ReturnValue(r)) => l == r,
+            (Object::Array { elements: l }, Object::Array { elements: r }) => l == r,
+            (Object::Hash { pairs: l }, Object::Hash { pairs: r }) => {
+                l.len() == r.len()
+                    && l.iter()
+                        .all(|(key, val)| r.iter().any(|(k, v)| k == key && v == val))
+            }
+            _ => false,
         }
     }
 }
 
 impl Object {
+    /// Name of the value's type, as returned by the `type` builtin and used in error messages.
     pub fn obj_type(&self) -> String {
         match self {
             Object::Integer(_) => "INTEGER".to_string(),
+            Object::Float(_) => "FLOAT".to_string(),
             Object::Boolean(_) => "BOOLEAN".to_string(),
             Object::String(_) => "STRING".to_string(),
             Object::Null => "NULL".to_string(),
-            Object::ReturnValue(_) => todo!(),
-            Object::FunctionLiteral { .. } => "FunctionLiteral".to_string(),
+            Object::ReturnValue(obj) => obj.obj_type(),
+            Object::Break => "BREAK".to_string(),
+            Object::Continue => "CONTINUE".to_string(),
+            Object::FunctionLiteral { .. } => "FUNCTION".to_string(),
             Object::BuiltIn(_) => "BUILTIN".to_string(),
             Object::Array { .. } => "ARRAY".to_string(),
+            Object::Hash { .. } => "HASH".to_string(),
         }
     }
-    pub fn is_truthy(&mut self) -> bool {
+    pub fn check_hashable(&self) -> Result<()> {
+        match self {
+            Object::Integer(_) | Object::Boolean(_) | Object::String(_) => Ok(()),
+            obj => Err(MonkeyError::UnusableHashKey(obj.obj_type())),
+        }
+    }
+    /// Whether this is a `return`, `break` or `continue` signal that ends the enclosing block early.
+    pub fn is_control_flow(&self) -> bool {
+        matches!(
+            self,
+            Object::ReturnValue(_) | Object::Break | Object::Continue
+        )
+    }
+    pub fn is_truthy(&self) -> bool {
         match self {
             Object::Null => false,
             Object::Boolean(val) => *val,
@@ -81,3 +153,149 @@ impl Object {
         }
     }
 }
+
+impl From<i64> for Object {
+    fn from(val: i64) -> Self {
+        Object::Integer(val)
+    }
+}
+
+impl From<bool> for Object {
+    fn from(val: bool) -> Self {
+        Object::Boolean(val)
+    }
+}
+
+impl From<&str> for Object {
+    fn from(val: &str) -> Self {
+        Object::String(val.to_string())
+    }
+}
+
+impl From<String> for Object {
+    fn from(val: String) -> Self {
+        Object::String(val)
+    }
+}
+
+impl From<Vec<Object>> for Object {
+    fn from(elements: Vec<Object>) -> Self {
+        Object::Array { elements }
+    }
+}
+
+impl TryFrom<Object> for i64 {
+    type Error = MonkeyError;
+
+    fn try_from(obj: Object) -> Result<Self> {
+        match obj {
+            Object::Integer(val) => Ok(val),
+            obj => Err(conversion_error("INTEGER", &obj)),
+        }
+    }
+}
+
+impl TryFrom<Object> for bool {
+    type Error = MonkeyError;
+
+    fn try_from(obj: Object) -> Result<Self> {
+        match obj {
+            Object::Boolean(val) => Ok(val),
+            obj => Err(conversion_error("BOOLEAN", &obj)),
+        }
+    }
+}
+
+impl TryFrom<Object> for String {
+    type Error = MonkeyError;
+
+    fn try_from(obj: Object) -> Result<Self> {
+        match obj {
+            Object::String(val) => Ok(val),
+            obj => Err(conversion_error("STRING", &obj)),
+        }
+    }
+}
+
+fn conversion_error(expected: &str, obj: &Object) -> MonkeyError {
+    MonkeyError::Custom(format!("expected {}, got {}", expected, obj.obj_type()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_hashable() {
+        assert!(Object::Integer(1).check_hashable().is_ok());
+        assert!(Object::Boolean(false).check_hashable().is_ok());
+        assert!(Object::String("a".to_string()).check_hashable().is_ok());
+        assert_eq!(
+            Object::Array { elements: vec![] }
+                .check_hashable()
+                .unwrap_err()
+                .to_string(),
+            "unusable as hash key: ARRAY"
+        );
+    }
+
+    #[test]
+    fn test_conversion() {
+        assert_eq!(Object::from(5), Object::Integer(5));
+        assert_eq!(i64::try_from(Object::from(5)).unwrap(), 5);
+        assert_eq!(Object::from(true), Object::Boolean(true));
+        assert!(bool::try_from(Object::from(true)).unwrap());
+        assert_eq!(Object::from("a"), Object::String("a".to_string()));
+        assert_eq!(
+            String::try_from(Object::from("a".to_string())).unwrap(),
+            "a"
+        );
+        let arr = Object::from(vec![1.into(), "b".into()]);
+        assert_eq!(arr.to_string(), r#"[1, "b"]"#);
+
+        assert_eq!(
+            i64::try_from(Object::from("1")).unwrap_err().to_string(),
+            "expected INTEGER, got STRING"
+        );
+        assert_eq!(
+            bool::try_from(Object::Null).unwrap_err().to_string(),
+            "expected BOOLEAN, got NULL"
+        );
+        assert_eq!(
+            String::try_from(arr).unwrap_err().to_string(),
+            "expected STRING, got ARRAY"
+        );
+    }
+
+    #[test]
+    fn test_seed_environment() {
+        let mut e = Evaluator::new();
+        e.set("x".into(), 5.into());
+        e.set("name".into(), "monkey".into());
+        let r = e.run(r#"name + ": " + str(x * 2)"#).unwrap();
+        assert_eq!(String::try_from(r).unwrap(), "monkey: 10");
+    }
+
+    #[test]
+    fn test_obj_type() {
+        let func = crate::eval_str("fn(x) { x }").unwrap();
+        let builtin = crate::builtin::lookup("len").unwrap();
+        let case = [
+            (Object::Integer(1), "INTEGER"),
+            (Object::Float(1.5), "FLOAT"),
+            (Object::Boolean(true), "BOOLEAN"),
+            (Object::String("a".to_string()), "STRING"),
+            (Object::Null, "NULL"),
+            (Object::ReturnValue(Box::new(Object::Null)), "NULL"),
+            (Object::Break, "BREAK"),
+            (Object::Continue, "CONTINUE"),
+            (func, "FUNCTION"),
+            (builtin, "BUILTIN"),
+            (Object::Array { elements: vec![] }, "ARRAY"),
+            (Object::Hash { pairs: vec![] }, "HASH"),
+        ];
+        for (obj, expected) in case.iter() {
+            assert_eq!(obj.obj_type(), *expected);
+        }
+    }
+}
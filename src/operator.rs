@@ -3,6 +3,10 @@ use std::fmt;
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Precedence {
     Lowest,
+    Conditional,
+    Coalesce,
+    LogicalOr,
+    LogicalAnd,
     Equals,
     LessGreater,
     Sum,
@@ -13,6 +17,7 @@ pub enum Precedence {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Prefix {
     Minus,
     Bang,
@@ -28,11 +33,17 @@ impl fmt::Display for Prefix {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Infix {
     Eq,
     NotEq,
     Lt,
     Gt,
+    Le,
+    Ge,
+    Coalesce,
+    And,
+    Or,
     Plus,
     Minus,
     Slash,
@@ -44,8 +55,13 @@ impl fmt::Display for Infix {
         match self {
             Infix::Eq => write!(f, "=="),
             Infix::NotEq => write!(f, "!="),
-            Infix::Lt => write!(f, ">"),
-            Infix::Gt => write!(f, "<"),
+            Infix::Gt => write!(f, ">"),
+            Infix::Lt => write!(f, "<"),
+            Infix::Le => write!(f, "<="),
+            Infix::Ge => write!(f, ">="),
+            Infix::Coalesce => write!(f, "??"),
+            Infix::And => write!(f, "&&"),
+            Infix::Or => write!(f, "||"),
             Infix::Plus => write!(f, "+"),
             Infix::Minus => write!(f, "-"),
             Infix::Slash => write!(f, "/"),
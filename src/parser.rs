@@ -3,7 +3,7 @@ use crate::{
     error::{MonkeyError, Result},
     lexer::Lexer,
     operator::{Infix, Precedence, Prefix},
-    token::Token,
+    token::{Span, Token},
 };
 
 #[derive(Debug)]
@@ -11,6 +11,8 @@ pub struct Parser<'a> {
     l: Lexer<'a>,
     cur_token: Token,
     peek_token: Token,
+    cur_span: Span,
+    peek_span: Span,
 }
 
 impl<'a> Parser<'a> {
@@ -19,6 +21,8 @@ impl<'a> Parser<'a> {
             l,
             cur_token: Token::Illegal('\u{0}'.to_string()),
             peek_token: Token::Illegal('\u{0}'.to_string()),
+            cur_span: Span::default(),
+            peek_span: Span::default(),
         };
         p.next_token();
         p.next_token();
@@ -27,7 +31,8 @@ impl<'a> Parser<'a> {
 
     pub fn next_token(&mut self) -> &Token {
         self.cur_token = self.peek_token.clone();
-        self.peek_token = self.l.next_token();
+        self.cur_span = self.peek_span;
+        (self.peek_token, self.peek_span) = self.l.next_token_with_span();
         &self.cur_token
     }
 
@@ -43,10 +48,38 @@ impl<'a> Parser<'a> {
         Ok(program)
     }
 
+    /// Parses the whole input without stopping at the first error.
+    /// After a failing statement the parser skips to the next `;` and carries on,
+    /// returning the statements it could parse along with every error.
+    pub fn parse_program_collect(&mut self) -> (Program, Vec<MonkeyError>) {
+        let mut program = Program::new();
+        let mut errors = Vec::new();
+        while self.cur_token != Token::Eof {
+            match self.parse_stmt() {
+                Ok(stmt) => program.stmts.push(stmt),
+                Err(err) => {
+                    errors.push(err);
+                    self.skip_to_semicolon();
+                }
+            }
+            self.next_token();
+        }
+        (program, errors)
+    }
+
+    fn skip_to_semicolon(&mut self) {
+        while !self.cur_token_is(Token::Semicolon) && !self.cur_token_is(Token::Eof) {
+            self.next_token();
+        }
+    }
+
     pub fn parse_stmt(&mut self) -> Result<Stmt> {
         match self.cur_token {
             Token::Let => self.parse_let_stmt(),
             Token::Return => self.parse_return_stmt(),
+            Token::While => self.parse_while_stmt(),
+            Token::For => self.parse_for_stmt(),
+            Token::Break | Token::Continue => self.parse_loop_control_stmt(),
             _ => self.parse_expr_statement(),
         }
     }
@@ -54,12 +87,24 @@ impl<'a> Parser<'a> {
     fn parse_let_stmt(&mut self) -> Result<Stmt> {
         self.next_token();
         let ident = match self.cur_token.clone() {
-            Token::Ident(ident) => ident,
+            Token::Ident(ident) => Expr::Ident(ident),
+            // `let [a, b] = ...` destructures an array into the listed names
+            Token::LBracket => {
+                let pattern = self.parse_array_literal()?;
+                if let Expr::ArrayLiteral { elements } = &pattern {
+                    if let Some(el) = elements.iter().find(|el| !matches!(el, Expr::Ident(_))) {
+                        return Err(MonkeyError::Custom(format!(
+                            "invalid destructuring target: {}",
+                            el
+                        )));
+                    }
+                }
+                pattern
+            }
             tok => {
-                return Err(MonkeyError::UnexpectedToken(
-                    tok,
-                    Token::Ident("".to_string()),
-                ))
+                return Err(illegal_character(&tok, self.cur_span).unwrap_or_else(|| {
+                    MonkeyError::UnexpectedToken(Token::Ident("".to_string()), tok, self.cur_span)
+                }))
             }
         };
         self.expect_peek(Token::Assign)?;
@@ -68,10 +113,7 @@ impl<'a> Parser<'a> {
         if self.peek_token_is(Token::Semicolon) {
             self.next_token();
         }
-        Ok(Stmt::LetStatement {
-            ident: Expr::Ident(ident),
-            value,
-        })
+        Ok(Stmt::LetStatement { ident, value })
     }
 
     fn parse_return_stmt(&mut self) -> Result<Stmt> {
@@ -96,44 +138,138 @@ impl<'a> Parser<'a> {
             }
             self.next_token();
         }
-        self.next_token();
         Ok(Stmt::BlockStatement { stmts })
     }
 
+    fn parse_while_stmt(&mut self) -> Result<Stmt> {
+        self.expect_peek(Token::LParen)?;
+        self.next_token();
+        let condition = self.parse_expression(Precedence::Lowest)?;
+        self.expect_peek(Token::RParen)?;
+        let body = self.parse_block_stmt()?;
+        if self.peek_token_is(Token::Semicolon) {
+            self.next_token();
+        }
+        Ok(Stmt::WhileStatement {
+            condition,
+            body: Box::new(body),
+        })
+    }
+
+    fn parse_for_stmt(&mut self) -> Result<Stmt> {
+        self.expect_peek(Token::LParen)?;
+        self.next_token();
+        let init = self.parse_stmt()?;
+        if !self.cur_token_is(Token::Semicolon) {
+            return Err(MonkeyError::UnexpectedToken(
+                Token::Semicolon,
+                self.cur_token.clone(),
+                self.cur_span,
+            ));
+        }
+        self.next_token();
+        let condition = self.parse_expression(Precedence::Lowest)?;
+        self.expect_peek(Token::Semicolon)?;
+        self.next_token();
+        let post = self.parse_stmt()?;
+        self.expect_peek(Token::RParen)?;
+        let body = self.parse_block_stmt()?;
+        if self.peek_token_is(Token::Semicolon) {
+            self.next_token();
+        }
+        Ok(Stmt::ForStatement {
+            init: Box::new(init),
+            condition,
+            post: Box::new(post),
+            body: Box::new(body),
+        })
+    }
+
+    fn parse_loop_control_stmt(&mut self) -> Result<Stmt> {
+        let stmt = match self.cur_token {
+            Token::Break => Stmt::BreakStatement,
+            _ => Stmt::ContinueStatement,
+        };
+        if self.peek_token_is(Token::Semicolon) {
+            self.next_token();
+        }
+        Ok(stmt)
+    }
+
     fn parse_expr_statement(&mut self) -> Result<Stmt> {
         let expr = self.parse_expression(Precedence::Lowest)?;
+        if self.peek_token_is(Token::Assign) {
+            return self.parse_assign_stmt(expr);
+        }
         if self.peek_token_is(Token::Semicolon) {
             self.next_token();
         }
         Ok(Stmt::ExpressionStatement { expr })
     }
 
+    fn parse_assign_stmt(&mut self, target: Expr) -> Result<Stmt> {
+        if !is_assignable(&target) {
+            return Err(MonkeyError::Custom(format!(
+                "invalid assignment target: {}",
+                target
+            )));
+        }
+        self.expect_peek(Token::Assign)?;
+        self.next_token();
+        let value = self.parse_expression(Precedence::Lowest)?;
+        if self.peek_token_is(Token::Semicolon) {
+            self.next_token();
+        }
+        Ok(Stmt::AssignStatement { target, value })
+    }
+
     fn parse_expression(&mut self, precedence: Precedence) -> Result<Expr> {
         let mut left = match self.cur_token.clone() {
             Token::Ident(ident) => Expr::Ident(ident),
             Token::String(val) => Expr::String(val),
             Token::Int(val) => Expr::Int(val),
+            Token::Float(val) => Expr::Float(val),
             Token::True => Expr::Boolean(true),
             Token::False => Expr::Boolean(false),
+            Token::Null => Expr::Null,
             Token::Minus | Token::Bang => self.parse_prefix_expression()?,
             Token::LParen => self.parse_group_expression()?,
             Token::If => self.parse_if_expression()?,
             Token::Function => self.parse_func()?,
             Token::LBrace => self.parse_hash_literal()?,
             Token::LBracket => self.parse_array_literal()?,
-            e => return Err(MonkeyError::Custom(format!("{:?}", e))),
+            e => {
+                return Err(illegal_character(&e, self.cur_span)
+                    .unwrap_or(MonkeyError::NoPrefixParse(e, self.cur_span)))
+            }
         };
         while !self.cur_token_is(Token::Semicolon) && precedence < self.peek_precedence() {
             self.next_token();
             left = match self.cur_token {
                 Token::LParen => self.parse_call_expression(left)?,
                 Token::LBracket => self.parse_index_expression(left)?,
+                Token::Question => self.parse_conditional_expression(left)?,
                 _ => self.parse_infix_expression(left)?,
             }
         }
         Ok(left)
     }
 
+    /// Parses `? consequence : alternative` after `condition`.
+    /// The alternative may itself be a conditional, so `a ? b : c ? d : e` nests to the right.
+    fn parse_conditional_expression(&mut self, condition: Expr) -> Result<Expr> {
+        self.next_token();
+        let consequence = self.parse_expression(Precedence::Lowest)?;
+        self.expect_peek(Token::Colon)?;
+        self.next_token();
+        let alternative = self.parse_expression(Precedence::Lowest)?;
+        Ok(Expr::ConditionalExpr {
+            condition: Box::new(condition),
+            consequence: Box::new(consequence),
+            alternative: Box::new(alternative),
+        })
+    }
+
     fn parse_prefix_expression(&mut self) -> Result<Expr> {
         let op = match self.cur_token {
             Token::Minus => Prefix::Minus,
@@ -156,8 +292,13 @@ impl<'a> Parser<'a> {
             Token::Asterisk => Infix::Asterisk,
             Token::Eq => Infix::Eq,
             Token::NotEq => Infix::NotEq,
-            Token::Lt => Infix::Lt,
             Token::Gt => Infix::Gt,
+            Token::Lt => Infix::Lt,
+            Token::Le => Infix::Le,
+            Token::Ge => Infix::Ge,
+            Token::DoubleQuestion => Infix::Coalesce,
+            Token::And => Infix::And,
+            Token::Or => Infix::Or,
             _ => return Err(MonkeyError::Custom("not yet".to_string())),
         };
         let precedence = self.cur_precedence();
@@ -173,12 +314,7 @@ impl<'a> Parser<'a> {
     fn parse_group_expression(&mut self) -> Result<Expr> {
         self.next_token();
         let expr = self.parse_expression(Precedence::Lowest)?;
-        if !self.expect_peek(Token::RParen)? {
-            return Err(MonkeyError::UnexpectedToken(
-                Token::RParen,
-                self.peek_token.clone(),
-            ));
-        }
+        self.expect_peek(Token::RParen)?;
         Ok(expr)
     }
 
@@ -200,7 +336,8 @@ impl<'a> Parser<'a> {
 
         let consequence = self.parse_block_stmt()?;
         let mut alternative = None;
-        if self.cur_token_is(Token::Else) {
+        if self.peek_token_is(Token::Else) {
+            self.next_token();
             alternative = Some(Box::new(self.parse_block_stmt()?));
         }
         Ok(Expr::IfExpr {
@@ -258,6 +395,10 @@ impl<'a> Parser<'a> {
         args.push(first_arg);
         while self.peek_token_is(Token::Comma) {
             self.next_token();
+            // a single trailing comma before the closing delimiter is allowed
+            if self.peek_token_is(end.clone()) {
+                break;
+            }
             self.next_token();
             let arg = self.parse_expression(Precedence::Lowest)?;
             args.push(arg);
@@ -273,22 +414,19 @@ impl<'a> Parser<'a> {
 
     pub fn parse_hash_literal(&mut self) -> Result<Expr> {
         let mut pairs: Vec<(Expr, Expr)> = Vec::new();
-        while !self.peek_token_is(Token::RBrace) {
+        // stopping at the end of input reports a missing `}` rather than a missing `,`
+        while !self.peek_token_is(Token::RBrace) && !self.peek_token_is(Token::Eof) {
             self.next_token();
             let key = self.parse_expression(Precedence::Lowest)?;
-            if !self.expect_peek(Token::Colon)? {
-                todo!()
-            };
+            self.expect_peek(Token::Colon)?;
             self.next_token();
             let value = self.parse_expression(Precedence::Lowest)?;
             pairs.push((key, value));
-            if !self.peek_token_is(Token::RBrace) && !self.expect_peek(Token::Comma)? {
-                todo!()
+            if !self.peek_token_is(Token::RBrace) && !self.peek_token_is(Token::Eof) {
+                self.expect_peek(Token::Comma)?;
             }
         }
-        if !self.expect_peek(Token::RBrace)? {
-            todo!()
-        }
+        self.expect_peek(Token::RBrace)?;
         Ok(Expr::HashLiteral { pairs })
     }
 
@@ -300,16 +438,18 @@ impl<'a> Parser<'a> {
         self.peek_token == t
     }
 
-    fn expect_peek(&mut self, t: Token) -> Result<bool> {
+    /// Advances past the next token if it is `t`, and errors without advancing otherwise.
+    fn expect_peek(&mut self, t: Token) -> Result<()> {
         let expected = t.clone();
         if self.peek_token_is(t) {
             self.next_token();
-            return Ok(true);
+            return Ok(());
         }
-        Err(MonkeyError::UnexpectedToken(
-            expected,
-            self.peek_token.clone(),
-        ))
+        Err(
+            illegal_character(&self.peek_token, self.peek_span).unwrap_or_else(|| {
+                MonkeyError::UnexpectedToken(expected, self.peek_token.clone(), self.peek_span)
+            }),
+        )
     }
 
     fn peek_precedence(&mut self) -> Precedence {
@@ -320,6 +460,28 @@ impl<'a> Parser<'a> {
     }
 }
 
+/// A variable, or an element of one such as `a[0]["k"]`.
+fn is_assignable(target: &Expr) -> bool {
+    match target {
+        Expr::Ident(_) => true,
+        Expr::IndexExpr { left, .. } => is_assignable(left),
+        _ => false,
+    }
+}
+
+/// Reports a stray character the lexer couldn't make a token of as such,
+/// rather than as whatever token the parser was expecting in its place.
+fn illegal_character(token: &Token, span: Span) -> Option<MonkeyError> {
+    let Token::Illegal(val) = token else {
+        return None;
+    };
+    let mut chars = val.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(MonkeyError::IllegalCharacter(c, span)),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -331,7 +493,7 @@ let y = 10;
 let foobar = 838383;
 let foo = "bar"
 "#;
-        let expected = vec![
+        let expected = [
             "let x = 5",
             "let y = 10",
             "let foobar = 838383",
@@ -346,13 +508,52 @@ let foo = "bar"
             assert_eq!(stmt.to_string(), expected[i])
         }
     }
+    #[test]
+    fn test_assign_stmt() {
+        let input = r#"x = 5;
+        x = x + 1
+        let y = 1;
+        y == 1;
+        a[0] = 1;
+        h["k"][i + 1] = 2;"#;
+        let expected = [
+            "x = 5",
+            "x = (x + 1)",
+            "let y = 1",
+            "(y == 1)",
+            "(a[0]) = 1",
+            r#"((h["k"])[(i + 1)]) = 2"#,
+        ];
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program().unwrap();
+        assert_eq!(program.stmts.len(), expected.len());
+        for (i, stmt) in program.stmts.iter().enumerate() {
+            assert_eq!(stmt.to_string(), expected[i])
+        }
+
+        let l = Lexer::new("1 = 2;");
+        let mut p = Parser::new(l);
+        assert_eq!(
+            p.parse_program().unwrap_err().to_string(),
+            "stmt error: invalid assignment target: 1"
+        );
+
+        let l = Lexer::new("f()[0] = 2;");
+        let mut p = Parser::new(l);
+        assert_eq!(
+            p.parse_program().unwrap_err().to_string(),
+            "stmt error: invalid assignment target: (f()[0])"
+        );
+    }
+
     #[test]
     fn test_return_stmt() {
         let input = r#"return 5;
 return 10;
 return "10"
 "#;
-        let expected = vec!["return 5", "return 10", r#"return "10""#];
+        let expected = ["return 5", "return 10", r#"return "10""#];
         let l = Lexer::new(input);
         let mut p = Parser::new(l);
         let program = p.parse_program().unwrap();
@@ -365,7 +566,20 @@ return "10"
     #[test]
     fn test_ident_expression() {
         let input = r#"let foobar = "foo""#;
-        let expected = vec![r#"let foobar = "foo""#];
+        let expected = [r#"let foobar = "foo""#];
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program().unwrap();
+        assert_eq!(program.stmts.len(), expected.len());
+        for (i, stmt) in program.stmts.iter().enumerate() {
+            assert_eq!(stmt.to_string(), expected[i])
+        }
+    }
+
+    #[test]
+    fn test_ident_with_digits() {
+        let input = "let foo_bar1 = _x + y2;";
+        let expected = ["let foo_bar1 = (_x + y2)"];
         let l = Lexer::new(input);
         let mut p = Parser::new(l);
         let program = p.parse_program().unwrap();
@@ -378,7 +592,23 @@ return "10"
     #[test]
     fn test_int_expression() {
         let input = "5";
-        let expected = vec!["5"];
+        let expected = ["5"];
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program().unwrap();
+        assert_eq!(program.stmts.len(), expected.len());
+        for (i, stmt) in program.stmts.iter().enumerate() {
+            assert_eq!(stmt.to_string(), expected[i])
+        }
+    }
+
+    #[test]
+    fn test_float_expression() {
+        let input = "1.25;
+        -0.5;
+        1.5 + 2;
+        3.0;";
+        let expected = ["1.25", "(-0.5)", "(1.5 + 2)", "3.0"];
         let l = Lexer::new(input);
         let mut p = Parser::new(l);
         let program = p.parse_program().unwrap();
@@ -391,8 +621,23 @@ return "10"
     #[test]
     fn test_prefix_expression() {
         let input = "-5;
-!5;";
-        let expected = vec!["(-5)", "(!5)"];
+!5;
+--5;
+5 - -5;
+-(5);
+-a * -b;
+-(1 + 2) * 3;
+!!true;";
+        let expected = [
+            "(-5)",
+            "(!5)",
+            "(-(-5))",
+            "(5 - (-5))",
+            "(-5)",
+            "((-a) * (-b))",
+            "((-(1 + 2)) * 3)",
+            "(!(!true))",
+        ];
         let l = Lexer::new(input);
         let mut p = Parser::new(l);
         let program = p.parse_program().unwrap();
@@ -416,8 +661,17 @@ return "10"
         5 > 4 == 3 < 4;
         5 < 4 != 3 > 4;
         3 + 4 * 5 == 3 * 1 + 4 * 5;
+        1 + 1 <= 2;
+        a >= b == true;
+        a ?? b == c;
+        a ?? b ?? c;
+        a ?? b ? c : d;
+        a || b && c == d;
+        a && b || c;
+        a ?? b || c;
+        not a and b or c;
         ";
-        let expected = vec![
+        let expected = [
             "(5 + 5)",
             "(5 - 5)",
             "(5 * 5)",
@@ -430,6 +684,15 @@ return "10"
             "((5 > 4) == (3 < 4))",
             "((5 < 4) != (3 > 4))",
             "((3 + (4 * 5)) == ((3 * 1) + (4 * 5)))",
+            "((1 + 1) <= 2)",
+            "((a >= b) == true)",
+            "(a ?? (b == c))",
+            "((a ?? b) ?? c)",
+            "((a ?? b) ? c : d)",
+            "(a || (b && (c == d)))",
+            "((a && b) || c)",
+            "(a ?? (b || c))",
+            "(((!a) && b) || c)",
         ];
         let l = Lexer::new(input);
         let mut p = Parser::new(l);
@@ -446,14 +709,18 @@ return "10"
         false;
         3 > 5 == false;
         3 < 5 == true;
-        !true
+        !true;
+        null;
+        x == null
         ";
-        let expected = vec![
+        let expected = [
             "true",
             "false",
             "((3 > 5) == false)",
             "((3 < 5) == true)",
             "(!true)",
+            "null",
+            "(x == null)",
         ];
         let l = Lexer::new(input);
         let mut p = Parser::new(l);
@@ -470,7 +737,7 @@ return "10"
         a * [1, 2, 3, 4][b * c] * d;
         add(a * b[2], b[1], 2 * [1, 2][1]);
         ";
-        let expected = vec![
+        let expected = [
             "[1, (2 * 2), (3 + 3)]",
             "((a * ([1, 2, 3, 4][(b * c)])) * d)",
             "add((a * (b[2])), (b[1]), (2 * ([1, 2][1])))",
@@ -483,6 +750,67 @@ return "10"
             assert_eq!(p.to_string(), expected[i]);
         }
     }
+
+    #[test]
+    fn test_index_expression() {
+        let input = "a[0][1];
+        a[0] + b[1];
+        -a[0];
+        f(x)[0][1] * 2;
+        a[i + 1][j];
+        ";
+        let expected = [
+            "((a[0])[1])",
+            "((a[0]) + (b[1]))",
+            "(-(a[0]))",
+            "(((f(x)[0])[1]) * 2)",
+            "((a[(i + 1)])[j])",
+        ];
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program().unwrap();
+        assert_eq!(program.stmts.len(), expected.len());
+        for (i, p) in program.stmts.iter().enumerate() {
+            assert_eq!(p.to_string(), expected[i]);
+        }
+    }
+
+    #[test]
+    fn test_trailing_comma() {
+        let input = r#"[1, 2, 3,];
+        [
+            1,
+            2,
+        ];
+        f(1, 2,);
+        {"a": 1, "b": 2,};
+        [];
+        f();
+        {};
+        "#;
+        let expected = [
+            "[1, 2, 3]",
+            "[1, 2]",
+            "f(1, 2)",
+            r#"{"a": 1, "b": 2}"#,
+            "[]",
+            "f()",
+            "{}",
+        ];
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program().unwrap();
+        assert_eq!(program.stmts.len(), expected.len());
+        for (i, p) in program.stmts.iter().enumerate() {
+            assert_eq!(p.to_string(), expected[i]);
+        }
+
+        for input in ["[,]", "f(,)", "{,}", "[1,,]", "f(1,,)"] {
+            let mut p = Parser::new(Lexer::new(input));
+            assert!(p.parse_program().is_err(), "{}", input);
+        }
+    }
+
     #[test]
     fn test_group() {
         let input = "1 + (2 + 3) + 4;
@@ -491,7 +819,7 @@ return "10"
         -(5 + 5);
         !(true == true);
         ";
-        let expected = vec![
+        let expected = [
             "((1 + (2 + 3)) + 4)",
             "((5 + 5) * 2)",
             "(2 / (5 + 5))",
@@ -510,7 +838,7 @@ return "10"
     #[test]
     fn test_string() {
         let input = r#""foobar""#;
-        let expected = vec![r#""foobar""#];
+        let expected = [r#""foobar""#];
         let l = Lexer::new(input);
         let mut p = Parser::new(l);
         let program = p.parse_program().unwrap();
@@ -524,7 +852,7 @@ return "10"
     fn test_if_expression() {
         let input = r#"if(x < y){x};
         if(a<b){a}else{b};"#;
-        let expected = vec!["if((x < y)){x}", "if((a < b)){a}else{b}"];
+        let expected = ["if((x < y)){x}", "if((a < b)){a}else{b}"];
         let l = Lexer::new(input);
         let mut p = Parser::new(l);
         let program = p.parse_program().unwrap();
@@ -534,11 +862,113 @@ return "10"
         }
     }
 
+    #[test]
+    fn test_while_stmt() {
+        let input = r#"while(x < 10){x};
+        while (true) { let a = 1; }
+        while (a) { b } c"#;
+        let expected = [
+            "while((x < 10)){x}",
+            "while(true){let a = 1}",
+            "while(a){b}",
+            "c",
+        ];
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program().unwrap();
+        assert_eq!(program.stmts.len(), expected.len());
+        for (i, p) in program.stmts.iter().enumerate() {
+            assert_eq!(p.to_string(), expected[i]);
+        }
+    }
+
+    #[test]
+    fn test_for_stmt() {
+        let input = r#"for (let i = 0; i < 10; i = i + 1) { puts(i); }
+        for (i = 0; true; i = i) {};
+        x"#;
+        let expected = [
+            "for(let i = 0; (i < 10); i = (i + 1)){puts(i)}",
+            "for(i = 0; true; i = i){}",
+            "x",
+        ];
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program().unwrap();
+        assert_eq!(program.stmts.len(), expected.len());
+        for (i, p) in program.stmts.iter().enumerate() {
+            assert_eq!(p.to_string(), expected[i]);
+        }
+
+        let l = Lexer::new("for (let i = 0 i < 10; i = i + 1) {}");
+        let mut p = Parser::new(l);
+        assert!(p.parse_program().is_err());
+    }
+
+    #[test]
+    fn test_break_continue() {
+        let input = "while (true) { break; continue }";
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program().unwrap();
+        assert_eq!(program.stmts.len(), 1);
+        assert_eq!(program.stmts[0].to_string(), "while(true){break; continue}");
+    }
+
+    #[test]
+    fn test_conditional_expression() {
+        let input = "true ? 1 : 2;
+        a < b ? a + 1 : b * 2;
+        a ? b : c ? d : e;
+        (a ? b : c) ? d : e;
+        let x = f(a ? 1 : 2, 3);";
+        let expected = [
+            "(true ? 1 : 2)",
+            "((a < b) ? (a + 1) : (b * 2))",
+            "(a ? b : (c ? d : e))",
+            "((a ? b : c) ? d : e)",
+            "let x = f((a ? 1 : 2), 3)",
+        ];
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program().unwrap();
+        assert_eq!(program.stmts.len(), expected.len());
+        for (i, stmt) in program.stmts.iter().enumerate() {
+            assert_eq!(stmt.to_string(), expected[i]);
+        }
+
+        let l = Lexer::new("true ? 1;");
+        let mut p = Parser::new(l);
+        assert!(p.parse_program().is_err());
+    }
+
+    #[test]
+    fn test_let_destructuring() {
+        let input = "let [a, b, c] = [1, 2, 3];
+        let [] = [];
+        let [x] = f();";
+        let expected = ["let [a, b, c] = [1, 2, 3]", "let [] = []", "let [x] = f()"];
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let program = p.parse_program().unwrap();
+        assert_eq!(program.stmts.len(), expected.len());
+        for (i, stmt) in program.stmts.iter().enumerate() {
+            assert_eq!(stmt.to_string(), expected[i]);
+        }
+
+        let l = Lexer::new("let [a, 1] = [1, 2];");
+        let mut p = Parser::new(l);
+        assert_eq!(
+            p.parse_program().unwrap_err().to_string(),
+            "stmt error: invalid destructuring target: 1"
+        );
+    }
+
     #[test]
     fn test_function_literal() {
         let input = r#"fn(x,y){x+y};
         fn(){1+1};"#;
-        let expected = vec!["fn(x,y){(x + y)}", "fn(){(1 + 1)}"];
+        let expected = ["fn(x,y){(x + y)}", "fn(){(1 + 1)}"];
         let l = Lexer::new(input);
         let mut p = Parser::new(l);
         let program = p.parse_program().unwrap();
@@ -550,8 +980,14 @@ return "10"
 
     #[test]
     fn test_call_expr() {
-        let input = r#"add(1, 2 * 3, 4 + 5);"#;
-        let expected = vec!["add(1, (2 * 3), (4 + 5))"];
+        let input = r#"add(1, 2 * 3, 4 + 5);
+        fn(x){x}(5);
+        fn(x, y){x}(1, 2)"#;
+        let expected = [
+            "add(1, (2 * 3), (4 + 5))",
+            "fn(x){x}(5)",
+            "fn(x,y){x}(1, 2)",
+        ];
         let l = Lexer::new(input);
         let mut p = Parser::new(l);
         let program = p.parse_program().unwrap();
@@ -567,7 +1003,7 @@ return "10"
         {};
         {"one": 0 + 1, "two": 10 - 8, "three": 15 / 5};
         "#;
-        let expected = vec![
+        let expected = [
             r#"{"one": 1, "two": 2, "three": 3}"#,
             "{}",
             r#"{"one": (0 + 1), "two": (10 - 8), "three": (15 / 5)}"#,
@@ -580,4 +1016,135 @@ return "10"
             assert_eq!(p.to_string(), expected[i]);
         }
     }
+
+    /// Parses `input`, renders it with `Display` and checks the rendering parses back
+    /// into the same AST.
+    fn assert_round_trip(input: &str) {
+        let program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+        let rendered = program.to_string();
+        let reparsed = Parser::new(Lexer::new(&rendered))
+            .parse_program()
+            .unwrap_or_else(|err| {
+                panic!("{:?} renders as unparseable {:?}: {}", input, rendered, err)
+            });
+        assert_eq!(program, reparsed, "{:?} renders as {:?}", input, rendered);
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        let case = [
+            "let a = 5; let b = a; b",
+            "let [a, b] = [1, 2];",
+            "a = a + 1;",
+            "return 5;",
+            r#"1; 2.5; "str"; true; false; null; x"#,
+            "-a; !b; -(1 + 2) * 3; (1 + 2) * 3",
+            "1 < 2; 1 > 2; 1 <= 2; 1 >= 2; 1 == 2; 1 != 2",
+            "a ?? b ?? c; a ? b : c ? d : e",
+            "a && b || not c; a and (b or c)",
+            "if (a) { b }; if (a) { let x = 1; x } else { c; d }",
+            "let f = fn() { 1 }; fn(x) { x }; fn(x, y) { let z = x + y; return z; }",
+            "f(); f(1); f(1, 2); f(1)(2)",
+            "[]; [1]; [1, 2 * 3][0]; a[1][2]",
+            r#"{}; {"a": 1, "b": [2]}["a"]"#,
+            "while (i < 3) { i = i + 1; if (i == 2) { break; } continue; }",
+            "for (let i = 0; i < 3; i = i + 1) { puts(i); }",
+            "let f = fn() { while (true) { return 1; } }; f()",
+        ];
+        for input in case.iter() {
+            assert_round_trip(input);
+        }
+        for entry in std::fs::read_dir("tests/codes").unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|ext| ext == "monkey") {
+                assert_round_trip(&std::fs::read_to_string(path).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_program_collect() {
+        let input = "let = 5; let x 5; let y = 1; y";
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let (program, errors) = p.parse_program_collect();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(program.stmts.len(), 2);
+        assert_eq!(program.stmts[0].to_string(), "let y = 1");
+        assert_eq!(program.stmts[1].to_string(), "y");
+
+        let l = Lexer::new("let a = 1; a + 1;");
+        let mut p = Parser::new(l);
+        let (program, errors) = p.parse_program_collect();
+        assert!(errors.is_empty());
+        assert_eq!(program.stmts.len(), 2);
+    }
+
+    #[test]
+    fn test_error_position() {
+        let input = "let a = 1;
+let b = 2;
+let c   3;";
+        let l = Lexer::new(input);
+        let mut p = Parser::new(l);
+        let err = p.parse_program().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "stmt error: expected '=', but got integer at line 3, col 9"
+        );
+
+        let l = Lexer::new("1 +\n\n  ;");
+        let mut p = Parser::new(l);
+        let (_, errors) = p.parse_program_collect();
+        assert_eq!(errors[0].to_string(), "unexpected ';' at line 3, col 3");
+    }
+
+    #[test]
+    fn test_error_message() {
+        let case = [
+            (
+                "let (x) = 1;",
+                "expected identifier, but got '(' at line 1, col 5",
+            ),
+            (
+                "f(1, 2",
+                "expected ')', but got end of input at line 1, col 7",
+            ),
+            ("let x = ;", "unexpected ';' at line 1, col 9"),
+            ("let x = }", "unexpected '}' at line 1, col 9"),
+            (
+                "if (true) { 1 } else 2",
+                "expected '{', but got integer at line 1, col 22",
+            ),
+            (
+                r#"{"a" 1}"#,
+                "expected ':', but got integer at line 1, col 6",
+            ),
+            (
+                r#"{"a": 1 "b": 2}"#,
+                "expected ',', but got string at line 1, col 9",
+            ),
+            (
+                r#"{"a": 1"#,
+                "expected '}', but got end of input at line 1, col 8",
+            ),
+            ("{", "expected '}', but got end of input at line 1, col 2"),
+            (
+                "(1 + 2",
+                "expected ')', but got end of input at line 1, col 7",
+            ),
+            ("1 & 2", "illegal character: '&' at line 1, col 3"),
+            ("let a = 1 | 2;", "illegal character: '|' at line 1, col 11"),
+            ("let & = 1;", "illegal character: '&' at line 1, col 5"),
+            ("f(1 & 2)", "illegal character: '&' at line 1, col 5"),
+            ("1.2.3", "unexpected illegal '1.2.3' at line 1, col 1"),
+        ];
+        for (input, expected) in case.iter() {
+            let l = Lexer::new(input);
+            let mut p = Parser::new(l);
+            let (_, errors) = p.parse_program_collect();
+            assert_eq!(errors.len(), 1, "{}", input);
+            assert_eq!(errors[0].to_string(), *expected);
+        }
+    }
 }
@@ -1,40 +1,120 @@
+use std::fmt;
+
 use crate::operator::Precedence;
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+/// Position of a token in the source, both 1-based.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Span {
+    pub line: usize,
+    pub col: usize,
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, col {}", self.line, self.col)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub enum Token {
     Illegal(String),
     Eof,
     Ident(String),
     String(String),
     Int(i64),
-    Assign,    // =
-    Plus,      // +
-    Minus,     // -
-    Asterisk,  // *
-    Slash,     // /
-    Gt,        // <
-    Lt,        // >
-    Comma,     // ,
-    Colon,     // :
-    Semicolon, // ;
-    LParen,    // (
-    RParen,    // )
-    LBrace,    // {
-    RBrace,    // }
-    LBracket,  // [
-    RBracket,  // ]
-    Bang,      // !
-    Eq,        // ==
-    NotEq,     // !=
+    Float(f64),
+    Assign,         // =
+    Plus,           // +
+    Minus,          // -
+    Asterisk,       // *
+    Slash,          // /
+    Lt,             // <
+    Gt,             // >
+    Comma,          // ,
+    Colon,          // :
+    Question,       // ?
+    DoubleQuestion, // ??
+    Semicolon,      // ;
+    LParen,         // (
+    RParen,         // )
+    LBrace,         // {
+    RBrace,         // }
+    LBracket,       // [
+    RBracket,       // ]
+    Bang,           // !
+    Eq,             // ==
+    NotEq,          // !=
+    Le,             // <=
+    Ge,             // >=
+    And,            // &&
+    Or,             // ||
 
     // keywords
     Function,
     Let,
     True,
     False,
+    Null,
     If,
     Else,
     Return,
+    While,
+    For,
+    Break,
+    Continue,
+}
+
+/// Renders a token for error messages: symbols and keywords in quotes,
+/// literals and identifiers by their category.
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            Token::Illegal(val) => return write!(f, "illegal '{}'", val),
+            Token::Eof => return write!(f, "end of input"),
+            Token::Ident(_) => return write!(f, "identifier"),
+            Token::String(_) => return write!(f, "string"),
+            Token::Int(_) => return write!(f, "integer"),
+            Token::Float(_) => return write!(f, "float"),
+            Token::Assign => "=",
+            Token::Plus => "+",
+            Token::Minus => "-",
+            Token::Asterisk => "*",
+            Token::Slash => "/",
+            Token::Lt => "<",
+            Token::Gt => ">",
+            Token::Comma => ",",
+            Token::Colon => ":",
+            Token::Question => "?",
+            Token::DoubleQuestion => "??",
+            Token::Semicolon => ";",
+            Token::LParen => "(",
+            Token::RParen => ")",
+            Token::LBrace => "{",
+            Token::RBrace => "}",
+            Token::LBracket => "[",
+            Token::RBracket => "]",
+            Token::Bang => "!",
+            Token::Eq => "==",
+            Token::NotEq => "!=",
+            Token::Le => "<=",
+            Token::Ge => ">=",
+            Token::And => "&&",
+            Token::Or => "||",
+            Token::Function => "fn",
+            Token::Let => "let",
+            Token::True => "true",
+            Token::False => "false",
+            Token::Null => "null",
+            Token::If => "if",
+            Token::Else => "else",
+            Token::Return => "return",
+            Token::While => "while",
+            Token::For => "for",
+            Token::Break => "break",
+            Token::Continue => "continue",
+        };
+        write!(f, "'{}'", symbol)
+    }
 }
 
 impl Token {
@@ -44,23 +124,38 @@ impl Token {
             "let" => Some(Token::Let),
             "true" => Some(Token::True),
             "false" => Some(Token::False),
+            "null" => Some(Token::Null),
             "if" => Some(Token::If),
             "else" => Some(Token::Else),
             "return" => Some(Token::Return),
+            "while" => Some(Token::While),
+            "for" => Some(Token::For),
+            "break" => Some(Token::Break),
+            "continue" => Some(Token::Continue),
+            // word spellings of the logical operators
+            "and" => Some(Token::And),
+            "or" => Some(Token::Or),
+            "not" => Some(Token::Bang),
             _ => None,
         }
     }
 
     pub fn precedence(tok: Token) -> Precedence {
         match tok {
+            Token::Question => Precedence::Conditional,
+            Token::DoubleQuestion => Precedence::Coalesce,
+            Token::Or => Precedence::LogicalOr,
+            Token::And => Precedence::LogicalAnd,
             Token::Eq => Precedence::Equals,
             Token::NotEq => Precedence::Equals,
             Token::Plus => Precedence::Sum,
             Token::Minus => Precedence::Sum,
             Token::Asterisk => Precedence::Product,
             Token::Slash => Precedence::Product,
-            Token::Lt => Precedence::LessGreater,
             Token::Gt => Precedence::LessGreater,
+            Token::Lt => Precedence::LessGreater,
+            Token::Le => Precedence::LessGreater,
+            Token::Ge => Precedence::LessGreater,
             Token::LParen => Precedence::Call,
             Token::LBracket => Precedence::Index,
             _ => Precedence::Lowest,
@@ -24,9 +24,122 @@ mod tests {
         assert_eq!(result, r#""Anna""#);
     }
 
-    // #[test]
-    // fn test_hash() {
-    //     let result = rmonkey::execute("tests/codes/hash.monkey");
-    //     assert_eq!(result, r#""Anna""#);
-    // }
+    #[test]
+    fn test_unsupported_extension() {
+        let result = rmonkey::execute("tests/codes/integer.txt");
+        assert_eq!(result, "unsupported file extension: .txt");
+        let result = rmonkey::execute("tests/codes/integer");
+        assert_eq!(result, "missing file extension: tests/codes/integer");
+    }
+
+    #[test]
+    fn test_try_execute() {
+        assert_eq!(
+            rmonkey::try_execute("tests/codes/integer.monkey").unwrap(),
+            "2"
+        );
+        let err = rmonkey::try_execute("tests/codes/missing.monkey").unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("cannot read tests/codes/missing.monkey"));
+        let err = rmonkey::try_execute("tests/codes/errors/syntax.monkey").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "stmt error: expected '=', but got integer at line 2, col 7"
+        );
+        let err = rmonkey::try_execute("tests/codes/errors/runtime.monkey").unwrap_err();
+        assert_eq!(err.to_string(), "type mismatch: INTEGER + BOOLEAN");
+        let err = rmonkey::try_execute("tests/codes/integer.txt").unwrap_err();
+        assert_eq!(err.to_string(), "unsupported file extension: .txt");
+        // `execute` gives the same errors as text
+        assert_eq!(
+            rmonkey::execute("tests/codes/errors/runtime.monkey"),
+            "type mismatch: INTEGER + BOOLEAN"
+        );
+    }
+
+    #[test]
+    fn test_eval_str() {
+        let result = rmonkey::eval_str("let add = fn(a, b) { a + b }; add(1, 2)");
+        assert_eq!(result.unwrap().to_string(), "3");
+        let result = rmonkey::eval_str("1 + true");
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "type mismatch: INTEGER + BOOLEAN"
+        );
+        // unbounded recursion is stopped before it runs out of native stack
+        let result = rmonkey::eval_str("let f = fn(n) { f(n + 1) }; f(0)");
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "stack overflow: more than 1000 nested calls"
+        );
+    }
+
+    #[test]
+    fn test_import() {
+        // main.monkey imports a function from lib/math.monkey, and two files importing each other
+        let result = rmonkey::execute("tests/codes/import/main.monkey");
+        assert_eq!(result, "18");
+        let mut e = rmonkey::evaluator::Evaluator::new();
+        e.allow_import = true;
+        let result = e.run(r#"import("tests/codes/import/lib/math.monkey"); square(3)"#);
+        assert_eq!(result.unwrap().to_string(), "9");
+        let result = e.run(r#"import("tests/codes/import/missing.monkey")"#);
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .starts_with("cannot import tests/codes/import/missing.monkey"));
+        let result = e.run("import(1)");
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "arg to `import` not supported, got INTEGER"
+        );
+        // only monkey files can be read
+        let result = e.run(r#"import("/etc/hostname")"#);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "cannot import /etc/hostname: only .monkey files can be imported"
+        );
+    }
+
+    #[test]
+    fn test_import_scope() {
+        // the imported names are global even when the import happens inside a function
+        let mut e = rmonkey::evaluator::Evaluator::new();
+        e.allow_import = true;
+        let result = e.run(
+            r#"let load = fn() { import("tests/codes/import/lib/math.monkey") }; load(); square(5)"#,
+        );
+        assert_eq!(result.unwrap().to_string(), "25");
+    }
+
+    #[test]
+    fn test_import_disabled() {
+        // code run from a string can't read files unless the host allows it
+        let result =
+            rmonkey::eval_str(r#"import("tests/codes/import/lib/math.monkey"); square(3)"#);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "cannot import tests/codes/import/lib/math.monkey: imports are disabled"
+        );
+    }
+
+    #[test]
+    fn test_check() {
+        assert!(rmonkey::check("let add = fn(a, b) { a + b }; add(1, 2)").is_empty());
+        // a syntax check doesn't evaluate, so runtime errors aren't reported
+        assert!(rmonkey::check("1 + true; undefined_name").is_empty());
+        let errors = rmonkey::check("let = 1;\nlet x 2;\nlet y = 3;\n)");
+        assert_eq!(errors.len(), 3);
+        assert_eq!(
+            errors[0].to_string(),
+            "expected identifier, but got '=' at line 1, col 5"
+        );
+    }
+
+    #[test]
+    fn test_hash() {
+        let result = rmonkey::execute("tests/codes/hash.monkey");
+        assert_eq!(result, r#""Anna""#);
+    }
 }